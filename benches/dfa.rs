@@ -0,0 +1,96 @@
+use lexer::bitparallel::BitParallel;
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::nfa::Nfa;
+use lexer::regex::*;
+use lexer::table::*;
+use std::hint::black_box;
+use std::time::Instant;
+
+// Steps each representation of one dfa over a few megabytes of text and
+// prints the throughput. Run with `cargo bench --bench dfa`.
+
+fn input() -> String {
+    let words = ["let", "x1", "=", "0x1f", "+", "foo_bar", "(", ")", ";", "while", "é", "42", "\n"];
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut text = String::new();
+
+    while text.len() < 4 << 20 {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        text.push_str(words[(seed % words.len() as u64) as usize]);
+        text.push(' ');
+    }
+
+    text
+}
+
+fn run<F: Fn(&str) -> usize>(name: &str, text: &str, f: F) {
+    let rounds = 5;
+    let start = Instant::now();
+    let mut count = 0;
+    for _ in 0..rounds {
+        count = black_box(f(black_box(text)));
+    }
+    let elapsed = start.elapsed().as_secs_f64() / rounds as f64;
+
+    println!("{:<12} {:>10.2} ms {:>10.1} MB/s  ({} matches)", name, elapsed * 1e3, text.len() as f64 / elapsed / 1e6, count);
+}
+
+fn main() {
+    let text = input();
+    let pattern = "[a-zA-Z_][a-zA-Z0-9_]*|0x[0-9a-f]+|[0-9]+";
+
+    let expression = Expression::from_str(&pattern.to_string());
+    let nfa = Nfa::from_expression(&expression.units);
+    let dfa = Dfa::from_nfa(&nfa.states, expression.get_chars());
+    let dense = DenseDfa::from_dfa(&dfa);
+    let sparse = SparseDfa::from_dfa(&dfa);
+
+    println!("{} states, dense {} bytes, sparse {} bytes", dfa.states.len(), dense.memory_usage(), sparse.memory_usage());
+
+    // Counts the maximal runs the full-match dfa accepts, restarting from
+    // the start state whenever it dies.
+    macro_rules! scan {
+        ($dfa:expr) => {
+            |text: &str| {
+                let mut state = $dfa.start_state();
+                let mut count = 0;
+                for ch in text.chars() {
+                    let next = $dfa.next_state(state, ch);
+                    state = if $dfa.is_dead_state(next) {
+                        if $dfa.is_match_state(state) {
+                            count += 1;
+                        }
+                        $dfa.next_state($dfa.start_state(), ch)
+                    } else {
+                        next
+                    };
+                }
+                count
+            }
+        };
+    }
+
+    run("hashset", &text, scan!(dfa));
+    run("dense", &text, scan!(dense));
+    run("sparse", &text, scan!(sparse));
+
+    let regex = RegexBuilder::new(pattern).build();
+    run("find_iter", &text, |text: &str| regex.find_iter(text).count());
+
+    // Small patterns built and searched once, as the cli does: the dfa path
+    // pays for its construction, the bit-parallel one only fills its tables.
+    for pattern in ["foo_bar [(]", "0x[0-9a-f]+ [+] 4", "(let|while) [a-z][a-z0-9_]* =", "x1 = 0x[0-9a-f]{3}"].iter() {
+        println!("{}", pattern);
+        run("  dfa", &text, |text: &str| {
+            let regex = RegexBuilder::new(pattern).build();
+            regex.find(text).map_or(0, |m| m.end())
+        });
+        run("  bits", &text, |text: &str| {
+            let matcher = BitParallel::new(pattern).expect("at most 64 positions");
+            matcher.find(text).map_or(0, |m| m.end())
+        });
+    }
+}
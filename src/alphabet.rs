@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use unit::*;
+
+// Splits the whole char space into disjoint classes, so that all chars of a
+// class are matched by exactly the same members of `chars`.
+pub fn classes(chars: &HashSet<Char>) -> Vec<Char> {
+    let chars: Vec<&Char> = chars.iter().collect();
+
+    let mut bounds: Vec<char> = vec!['\0'];
+    for ch in chars.iter() {
+        for (a, b) in ch.to_ranges() {
+            bounds.push(a);
+            if let Some(next) = next_char(b) {
+                bounds.push(next);
+            }
+        }
+    }
+    bounds.sort();
+    bounds.dedup();
+
+    let mut order: Vec<Vec<bool>> = Vec::new();
+    let mut groups: HashMap<Vec<bool>, Vec<(char, char)>> = HashMap::new();
+
+    for (i, &a) in bounds.iter().enumerate() {
+        let b = match bounds.get(i + 1) {
+            Some(&next) => prev_char(next).expect("bounds are sorted"),
+            None => std::char::MAX,
+        };
+
+        let signature: Vec<bool> = chars.iter().map(|ch| ch.is_match(a)).collect();
+        if !groups.contains_key(&signature) {
+            order.push(signature.clone());
+        }
+        groups.entry(signature).or_default().push((a, b));
+    }
+
+    order.iter()
+        .filter_map(|signature| Char::from_ranges(groups[signature].clone()))
+        .collect()
+}
+
+pub fn representative(class: &Char) -> char {
+    class.to_ranges()[0].0
+}
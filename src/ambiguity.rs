@@ -0,0 +1,95 @@
+use crate::dfa::Dfa;
+use crate::regex::Regex;
+
+// How many example strings are kept for each conflict.
+pub const EXAMPLES: usize = 3;
+
+// Two rules matching the same lexemes, `first` coming before `second`. The
+// examples are the first lexemes both match in shortlex order.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Overlap {
+    pub first: usize,
+    pub second: usize,
+    pub examples: Vec<String>,
+}
+
+// A rule whose every lexeme is also matched by rules before it, so it never
+// fires. `by` are the earlier rules it overlaps, empty if it matches nothing.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Shadowed {
+    pub rule: usize,
+    pub by: Vec<usize>,
+    pub examples: Vec<String>,
+}
+
+// The conflicts between the rules of a lexer, given in priority order: on a
+// lexeme more than one rule matches, the earliest of them wins.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Ambiguities {
+    pub overlaps: Vec<Overlap>,
+    pub shadowed: Vec<Shadowed>,
+}
+
+impl Ambiguities {
+    pub fn from_rules(rules: &[Regex]) -> Ambiguities {
+        let examples = |dfa: &Dfa| dfa.iter_strings().take(EXAMPLES).collect::<Vec<String>>();
+
+        let mut overlaps: Vec<Overlap> = Vec::new();
+        for (i, first) in rules.iter().enumerate() {
+            for (j, second) in rules.iter().enumerate().skip(i + 1) {
+                let both = first.dfa().intersection(second.dfa());
+                if !both.is_empty() {
+                    overlaps.push(Overlap { first: i, second: j, examples: examples(&both) });
+                }
+            }
+        }
+
+        // the rules before each one are joined into a single dfa as it goes
+        let mut shadowed: Vec<Shadowed> = Vec::new();
+        let mut earlier: Option<Dfa> = None;
+        for (j, rule) in rules.iter().enumerate() {
+            let fires = match &earlier {
+                Some(earlier) => !rule.dfa().difference(earlier).is_empty(),
+                None => !rule.dfa().is_empty(),
+            };
+            if !fires {
+                let by = overlaps.iter().filter(|overlap| overlap.second == j).map(|overlap| overlap.first).collect();
+                shadowed.push(Shadowed { rule: j, by, examples: examples(rule.dfa()) });
+            }
+
+            earlier = Some(match earlier {
+                Some(earlier) => earlier.union(rule.dfa()).minimize(),
+                None => rule.dfa().minimize(),
+            });
+        }
+
+        Ambiguities { overlaps, shadowed }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overlaps.is_empty() && self.shadowed.is_empty()
+    }
+
+    // One line per conflict, naming the rules by `names`.
+    pub fn render(&self, names: &[&str]) -> String {
+        let name = |rule: usize| format!("{} `{}`", rule, names[rule]);
+        let quote = |examples: &[String]| examples.iter().map(|example| format!("{:?}", example)).collect::<Vec<String>>().join(", ");
+
+        let mut lines: Vec<String> = Vec::new();
+        for overlap in self.overlaps.iter() {
+            lines.push(format!("rules {} and {} both match {}",
+                name(overlap.first), name(overlap.second), quote(&overlap.examples)));
+        }
+        for shadowed in self.shadowed.iter() {
+            if shadowed.by.is_empty() {
+                lines.push(format!("rule {} matches nothing", name(shadowed.rule)));
+            } else {
+                let by: Vec<String> = shadowed.by.iter().map(|&rule| name(rule)).collect();
+                lines.push(format!("rule {} never fires, it is shadowed by {}, e.g. on {}",
+                    name(shadowed.rule), by.join(" and "), quote(&shadowed.examples)));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,150 @@
+use crate::alphabet::*;
+use crate::dfa::*;
+use crate::fastate::*;
+use std::collections::VecDeque;
+
+impl Dfa {
+    fn targets(&self, state: usize) -> Vec<(char, usize)> {
+        let mut targets: Vec<(char, usize)> = self.states[state].trans.iter().filter_map(|tran| match tran {
+            Tran::Char(ch, to) => Some((representative(ch), *to)),
+            Tran::Epsilon(_to) => None,
+        }).collect();
+        targets.sort();
+
+        targets
+    }
+
+    // States both reachable from the head and leading to an accepting state.
+    // Only these lie on the path of some accepted string.
+    fn useful_states(&self) -> Vec<bool> {
+        let len = self.states.len();
+        let mut reachable: Vec<bool> = vec![false; len];
+        let mut stack: Vec<usize> = vec![self.head];
+        while let Some(t) = stack.pop() {
+            if !reachable[t] {
+                reachable[t] = true;
+                stack.extend(self.targets(t).iter().map(|&(_ch, to)| to));
+            }
+        }
+
+        let mut sources: Vec<Vec<usize>> = vec![Vec::new(); len];
+        for state in self.states.iter() {
+            for (_ch, to) in self.targets(state.index) {
+                sources[to].push(state.index);
+            }
+        }
+
+        let mut useful: Vec<bool> = vec![false; len];
+        let mut stack: Vec<usize> = self.tail.clone();
+        while let Some(t) = stack.pop() {
+            if !useful[t] && reachable[t] {
+                useful[t] = true;
+                stack.extend(sources[t].iter());
+            }
+        }
+
+        useful
+    }
+
+    // Useful states with every state after its targets, or `None` when they
+    // form a cycle.
+    fn useful_order(&self) -> Option<Vec<usize>> {
+        let useful = self.useful_states();
+        if !useful[self.head] {
+            return Some(vec![])
+        }
+
+        // 0 unvisited, 1 on the stack, 2 done
+        let mut color: Vec<u8> = vec![0; self.states.len()];
+        let mut order: Vec<usize> = Vec::new();
+        let mut stack: Vec<(usize, Vec<usize>)> = Vec::new();
+
+        color[self.head] = 1;
+        stack.push((self.head, self.targets(self.head).iter().map(|&(_ch, to)| to).collect()));
+
+        while let Some((t, targets)) = stack.last_mut() {
+            match targets.pop() {
+                Some(to) if useful[to] => match color[to] {
+                    0 => {
+                        color[to] = 1;
+                        let targets = self.targets(to).iter().map(|&(_ch, to)| to).collect();
+                        stack.push((to, targets));
+                    },
+                    1 => return None,
+                    _ => {},
+                },
+                Some(_to) => {},
+                None => {
+                    color[*t] = 2;
+                    order.push(*t);
+                    stack.pop();
+                },
+            }
+        }
+
+        Some(order)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.useful_states()[self.head]
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.useful_order().is_some()
+    }
+
+    // Breadth first search from the head, taking the trans of a state in
+    // order of their smallest char, so the string found is the first
+    // accepted one in shortlex order.
+    pub fn shortest_match(&self) -> Option<String> {
+        let mut parent: Vec<Option<(usize, char)>> = vec![None; self.states.len()];
+        let mut seen: Vec<bool> = vec![false; self.states.len()];
+        seen[self.head] = true;
+
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        queue.push_back(self.head);
+
+        while let Some(t) = queue.pop_front() {
+            if self.is_accepted(t) {
+                let mut ans: Vec<char> = Vec::new();
+                let mut cur = t;
+                while let Some((from, ch)) = parent[cur] {
+                    ans.push(ch);
+                    cur = from;
+                }
+
+                return Some(ans.iter().rev().collect())
+            }
+
+            for (ch, to) in self.targets(t) {
+                if !seen[to] {
+                    seen[to] = true;
+                    parent[to] = Some((t, ch));
+                    queue.push_back(to);
+                }
+            }
+        }
+
+        None
+    }
+
+    // The length in chars of the longest accepted string, or `None` when no
+    // string is accepted or there is no longest one.
+    pub fn longest_match_length(&self) -> Option<usize> {
+        let order = self.useful_order()?;
+        let useful = self.useful_states();
+
+        let mut longest: Vec<Option<usize>> = vec![None; self.states.len()];
+        for &t in order.iter() {
+            let mut best = if self.is_accepted(t) { Some(0) } else { None };
+            for (_ch, to) in self.targets(t) {
+                if useful[to] {
+                    best = best.max(longest[to].map(|len| len + 1));
+                }
+            }
+            longest[t] = best;
+        }
+
+        longest[self.head]
+    }
+}
@@ -0,0 +1,103 @@
+use crate::budget::*;
+use crate::fastate::*;
+use crate::nfa::*;
+
+// Per nfa state the fewest edits a thread reached it with, and among
+// those the earliest start. Adding the same edits to two threads keeps
+// their order, so keeping the least one per state loses no better match.
+type Threads = Vec<Option<(usize, usize)>>;
+
+fn relax(threads: &mut Threads, state: usize, thread: (usize, usize), max_edits: usize) -> bool {
+    if thread.0 > max_edits || threads[state].is_some_and(|old| old <= thread) {
+        return false
+    }
+
+    threads[state] = Some(thread);
+    true
+}
+
+// Follows the epsilon trans for free and the char trans for one edit each,
+// a char of the pattern missing from the text.
+fn close(threads: &mut Threads, states: &[FaState], max_edits: usize) {
+    let mut stack: Vec<usize> = (0..threads.len()).filter(|&i| threads[i].is_some()).collect();
+
+    while let Some(t) = stack.pop() {
+        let (edits, start) = threads[t].expect("only threads are pushed");
+        for tran in states[t].trans.iter() {
+            let (to, thread) = match tran {
+                Tran::Epsilon(to) => (*to, (edits, start)),
+                Tran::Char(_ch, to) => (*to, (edits + 1, start)),
+            };
+            if relax(threads, to, thread, max_edits) {
+                stack.push(to);
+            }
+        }
+    }
+}
+
+// Runs the nfa over the text allowing insertions, deletions and
+// substitutions, starting a thread at every char. Returns the start, end
+// and edits of the match with the fewest edits, the leftmost of those,
+// and the longest of those.
+pub fn find_approx(nfa: &Nfa, content: &str, max_edits: usize) -> Option<(usize, usize, usize)> {
+    find_approx_with(nfa, content, max_edits, &mut Unlimited).expect("Failed to find approximate match")
+}
+
+// As `find_approx`, taking a step from the budget for every thread moved.
+pub fn try_find_approx(nfa: &Nfa, content: &str, max_edits: usize, budget: &mut Budget) -> Result<Option<(usize, usize, usize)>, SearchError> {
+    find_approx_with(nfa, content, max_edits, budget)
+}
+
+fn find_approx_with<S: Steps>(nfa: &Nfa, content: &str, max_edits: usize, budget: &mut S) -> Result<Option<(usize, usize, usize)>, SearchError> {
+    let len = nfa.states.len();
+    let mut best: Option<(usize, usize, usize)> = None;
+
+    let mut threads: Threads = vec![None; len];
+    let mut chars = content.char_indices();
+    let mut at = 0;
+    loop {
+        relax(&mut threads, nfa.head, (0, at), max_edits);
+        close(&mut threads, &nfa.states, max_edits);
+
+        for (i, thread) in threads.iter().enumerate() {
+            if let (Some((edits, start)), true) = (thread, is_final(&nfa.states, i)) {
+                let better = match best {
+                    None => true,
+                    Some((best_start, best_end, best_edits)) => (*edits, *start) < (best_edits, best_start)
+                        || ((*edits, *start) == (best_edits, best_start) && at > best_end),
+                };
+                if better {
+                    best = Some((*start, at, *edits));
+                }
+            }
+        }
+
+        let ch = match chars.next() {
+            Some((_i, ch)) => ch,
+            None => break,
+        };
+
+        budget.step(threads.iter().filter(|thread| thread.is_some()).count().max(1))?;
+        let mut next: Threads = vec![None; len];
+        for (t, thread) in threads.iter().enumerate() {
+            let (edits, start) = match thread {
+                Some(thread) => *thread,
+                None => continue,
+            };
+
+            // a char of the text missing from the pattern
+            relax(&mut next, t, (edits + 1, start), max_edits);
+            for tran in nfa.states[t].trans.iter() {
+                if let Tran::Char(ch_, to) = tran {
+                    let cost = if ch_.is_match(ch) { 0 } else { 1 };
+                    relax(&mut next, *to, (edits + cost, start), max_edits);
+                }
+            }
+        }
+
+        threads = next;
+        at += ch.len_utf8();
+    }
+
+    Ok(best)
+}
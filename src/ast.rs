@@ -0,0 +1,389 @@
+use crate::dfa::*;
+use crate::expression::Expression;
+use crate::fastate::*;
+use unit::*;
+
+// A regular expression as a tree. The constructors below keep it in a
+// simplified form: no empty language or empty string inside a
+// concatenation, flat alternations without duplicates whose chars are
+// joined into one class, and no nested stars. `And` and `Not` are the
+// intersection `&` and the complement `~`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Ast {
+    Nothing,
+    Empty,
+    Char(Char),
+    Concat(Vec<Ast>),
+    Alternate(Vec<Ast>),
+    Star(Box<Ast>),
+    And(Vec<Ast>),
+    Not(Box<Ast>),
+}
+
+impl Ast {
+    pub fn is_nullable(&self) -> bool {
+        match self {
+            Ast::Nothing => false,
+            Ast::Empty => true,
+            Ast::Char(_ch) => false,
+            Ast::Concat(asts) => asts.iter().all(|ast| ast.is_nullable()),
+            Ast::Alternate(asts) => asts.iter().any(|ast| ast.is_nullable()),
+            Ast::Star(_ast) => true,
+            Ast::And(asts) => asts.iter().all(|ast| ast.is_nullable()),
+            Ast::Not(ast) => !ast.is_nullable(),
+        }
+    }
+
+    pub fn from_expression(expression: &[Unit]) -> Ast {
+        let mut asts: Vec<Ast> = Vec::new();
+        for unit in expression {
+            match unit {
+                Unit::Char(ch) => asts.push(Ast::Char(ch.clone())),
+                Unit::Operator(Operator::Concatenation()) => {
+                    let rhs = asts.pop().expect("Failed to get ast");
+                    let lhs = asts.pop().expect("Failed to get ast");
+                    asts.push(Ast::concat(lhs, rhs));
+                },
+                Unit::Operator(Operator::Alternation()) => {
+                    let rhs = asts.pop().expect("Failed to get ast");
+                    let lhs = asts.pop().expect("Failed to get ast");
+                    asts.push(Ast::alternate(lhs, rhs));
+                },
+                Unit::Operator(Operator::Intersection()) => {
+                    let rhs = asts.pop().expect("Failed to get ast");
+                    let lhs = asts.pop().expect("Failed to get ast");
+                    asts.push(Ast::and(lhs, rhs));
+                },
+                Unit::Operator(Operator::Complement()) => {
+                    let ast = asts.pop().expect("Failed to get ast");
+                    asts.push(Ast::complement(ast));
+                },
+                Unit::Operator(Operator::Repeat(repeat)) => {
+                    let ast = asts.pop().expect("Failed to get ast");
+                    asts.push(match repeat {
+                        Repeat::Exact(times) => Ast::repeat(ast, *times, Some(*times)),
+                        Repeat::FromZero() => Ast::star(ast),
+                        Repeat::From(from) => Ast::repeat(ast, *from, None),
+                        Repeat::FromTo(from, to) => Ast::repeat(ast, *from, Some(*to)),
+                        Repeat::Maybe() => Ast::alternate(Ast::Empty, ast),
+                    });
+                },
+                Unit::Operator(Operator::Capture(_index, _name)) => {},
+                _ => panic!("unexpected unit"),
+            }
+        }
+
+        asts.pop().expect("Generate Ast Error.")
+    }
+
+    // `ast` repeated from `min` to `max` times, without bound for `None`.
+    pub fn repeat(ast: Ast, min: usize, max: Option<usize>) -> Ast {
+        let mut res = Ast::Empty;
+        for _i in 0..min {
+            res = Ast::concat(res, ast.clone());
+        }
+
+        match max {
+            None => Ast::concat(res, Ast::star(ast)),
+            Some(max) => {
+                let mut rest = Ast::Empty;
+                for _i in min..max {
+                    rest = Ast::alternate(Ast::Empty, Ast::concat(ast.clone(), rest));
+                }
+                Ast::concat(res, rest)
+            },
+        }
+    }
+
+    pub fn concat(lhs: Ast, rhs: Ast) -> Ast {
+        let mut asts: Vec<Ast> = Vec::new();
+        for ast in [lhs, rhs] {
+            match ast {
+                Ast::Nothing => return Ast::Nothing,
+                Ast::Empty => {},
+                Ast::Concat(inner) => asts.extend(inner),
+                ast => asts.push(ast),
+            }
+        }
+
+        match asts.len() {
+            0 => Ast::Empty,
+            1 => asts.pop().expect("one ast"),
+            _ => Ast::Concat(asts),
+        }
+    }
+
+    pub fn alternate(lhs: Ast, rhs: Ast) -> Ast {
+        let mut asts: Vec<Ast> = Vec::new();
+        let mut ranges: Option<(usize, Vec<(char, char)>)> = None;
+
+        let members = vec![lhs, rhs].into_iter().flat_map(|ast| match ast {
+            Ast::Alternate(inner) => inner,
+            ast => vec![ast],
+        });
+        for ast in members {
+            match ast {
+                Ast::Nothing => {},
+                Ast::Char(ch) => match &mut ranges {
+                    Some((_at, ranges)) => ranges.extend(ch.to_ranges()),
+                    None => {
+                        ranges = Some((asts.len(), ch.to_ranges()));
+                        asts.push(Ast::Nothing);
+                    },
+                },
+                ast => {
+                    if !asts.contains(&ast) {
+                        asts.push(ast);
+                    }
+                },
+            }
+        }
+
+        if let Some((at, ranges)) = ranges {
+            asts[at] = Char::from_ranges(ranges).map_or(Ast::Nothing, Ast::Char);
+        }
+        // x+ or the empty string is x*
+        if asts.contains(&Ast::Empty) {
+            for ast in asts.iter_mut() {
+                if let Ast::Concat(inner) = ast {
+                    let star = match inner.as_slice() {
+                        [x, Ast::Star(y)] | [Ast::Star(y), x] if **y == *x => Some(Ast::Star(y.clone())),
+                        _ => None,
+                    };
+                    if let Some(star) = star {
+                        *ast = star;
+                    }
+                }
+            }
+        }
+        if asts.len() > 1 && asts.iter().any(|ast| *ast != Ast::Empty && ast.is_nullable()) {
+            asts.retain(|ast| *ast != Ast::Empty);
+        }
+
+        let everything = Ast::complement(Ast::Nothing);
+        if asts.contains(&everything) {
+            return everything
+        }
+
+        match asts.len() {
+            0 => Ast::Nothing,
+            1 => asts.pop().expect("one ast"),
+            _ => Ast::Alternate(asts),
+        }
+    }
+
+    pub fn star(ast: Ast) -> Ast {
+        match ast {
+            Ast::Nothing | Ast::Empty => Ast::Empty,
+            Ast::Star(inner) => Ast::Star(inner),
+            Ast::Alternate(asts) if asts.contains(&Ast::Empty) => {
+                let rest = asts.into_iter()
+                    .filter(|ast| *ast != Ast::Empty)
+                    .fold(Ast::Nothing, Ast::alternate);
+                Ast::star(rest)
+            },
+            ast => Ast::Star(Box::new(ast)),
+        }
+    }
+
+    pub fn and(lhs: Ast, rhs: Ast) -> Ast {
+        let mut asts: Vec<Ast> = Vec::new();
+        for ast in [lhs, rhs] {
+            match ast {
+                Ast::Nothing => return Ast::Nothing,
+                Ast::Not(inner) if *inner == Ast::Nothing => {},
+                Ast::And(inner) => asts.extend(inner),
+                ast => asts.push(ast),
+            }
+        }
+
+        let mut res: Vec<Ast> = Vec::new();
+        for ast in asts {
+            if !res.contains(&ast) {
+                res.push(ast);
+            }
+        }
+
+        match res.len() {
+            0 => Ast::complement(Ast::Nothing),
+            1 => res.pop().expect("one ast"),
+            _ => Ast::And(res),
+        }
+    }
+
+    pub fn complement(ast: Ast) -> Ast {
+        match ast {
+            Ast::Not(inner) => *inner,
+            ast => Ast::Not(Box::new(ast)),
+        }
+    }
+
+    // Rebuilds the tree with the members of every alternation and
+    // intersection in a fixed order, so that expressions equal up to the
+    // order of those members compare equal.
+    pub fn normalize(&self) -> Ast {
+        let sorted = |asts: &[Ast]| {
+            let mut asts: Vec<Ast> = asts.iter().map(|ast| ast.normalize()).collect();
+            asts.sort_by_cached_key(|ast| format!("{:?}", ast));
+            asts
+        };
+
+        match self {
+            Ast::Concat(asts) => asts.iter().map(|ast| ast.normalize()).fold(Ast::Empty, Ast::concat),
+            Ast::Alternate(asts) => sorted(asts).into_iter().fold(Ast::Nothing, Ast::alternate),
+            Ast::And(asts) => sorted(asts).into_iter().fold(Ast::complement(Ast::Nothing), Ast::and),
+            Ast::Star(ast) => Ast::star(ast.normalize()),
+            Ast::Not(ast) => Ast::complement(ast.normalize()),
+            ast => ast.clone(),
+        }
+    }
+
+    // Writes the tree as postfix units. An alternation with the empty string
+    // becomes `?` and `x x*` becomes `x+`. The syntax has no atom for the
+    // empty string or the empty language, so they are written `a{0}` and
+    // `a&~a`.
+    fn push_units(&self, units: &mut Vec<Unit>) {
+        match self {
+            Ast::Nothing => {
+                units.push(Unit::Char(Char::Single('a')));
+                units.push(Unit::Char(Char::Single('a')));
+                units.push(Unit::Operator(Operator::Complement()));
+                units.push(Unit::Operator(Operator::Intersection()));
+            },
+            Ast::Empty => {
+                units.push(Unit::Char(Char::Single('a')));
+                units.push(Unit::Operator(Operator::Repeat(Repeat::Exact(0))));
+            },
+            Ast::Char(ch) => units.push(Unit::Char(ch.clone())),
+            Ast::Concat(asts) => {
+                let mut i = 0;
+                let mut first = true;
+                while i < asts.len() {
+                    let plus = match (&asts[i], asts.get(i + 1)) {
+                        (Ast::Star(inner), Some(next)) => **inner == *next,
+                        (ast, Some(Ast::Star(inner))) => **inner == *ast,
+                        _ => false,
+                    };
+
+                    if plus {
+                        match &asts[i] {
+                            Ast::Star(inner) => inner.push_units(units),
+                            ast => ast.push_units(units),
+                        }
+                        units.push(Unit::Operator(Operator::Repeat(Repeat::From(1))));
+                        i += 2;
+                    } else {
+                        asts[i].push_units(units);
+                        i += 1;
+                    }
+
+                    if !first {
+                        units.push(Unit::Operator(Operator::Concatenation()));
+                    }
+                    first = false;
+                }
+            },
+            Ast::Alternate(asts) => {
+                let mut first = true;
+                for ast in asts.iter().filter(|ast| **ast != Ast::Empty) {
+                    ast.push_units(units);
+                    if !first {
+                        units.push(Unit::Operator(Operator::Alternation()));
+                    }
+                    first = false;
+                }
+                if asts.contains(&Ast::Empty) {
+                    units.push(Unit::Operator(Operator::Repeat(Repeat::Maybe())));
+                }
+            },
+            Ast::Star(ast) => {
+                ast.push_units(units);
+                units.push(Unit::Operator(Operator::Repeat(Repeat::FromZero())));
+            },
+            Ast::And(asts) => {
+                for (i, ast) in asts.iter().enumerate() {
+                    ast.push_units(units);
+                    if i > 0 {
+                        units.push(Unit::Operator(Operator::Intersection()));
+                    }
+                }
+            },
+            Ast::Not(ast) => {
+                ast.push_units(units);
+                units.push(Unit::Operator(Operator::Complement()));
+            },
+        }
+    }
+
+    // `None` for the empty language. Inside a larger tree the empty language
+    // is written `a&~a` and the empty string `a{0}`, so that `~` of the
+    // empty language, which is every string, prints as `~(a&~a)`.
+    pub fn to_expression(&self) -> Option<Expression> {
+        if *self == Ast::Nothing {
+            return None
+        }
+
+        let mut units: Vec<Unit> = Vec::new();
+        self.push_units(&mut units);
+
+        Some(Expression { units })
+    }
+}
+
+impl Dfa {
+    // State elimination over the useful states plus a fresh start and a
+    // fresh final state. The state with the fewest paths through it goes
+    // first, which keeps the intermediate expressions small. `None` stands
+    // for the empty language, which no pattern denotes. The syntax has no
+    // atom for the empty string either, so a language holding only it is
+    // written `a{0}`, a char repeated zero times.
+    pub fn to_regex(&self) -> Option<Expression> {
+        let dfa = self.minimize();
+        let len = dfa.states.len();
+        let (start, end) = (len, len + 1);
+
+        let mut edges: Vec<Vec<Ast>> = vec![vec![Ast::Nothing; len + 2]; len + 2];
+        edges[start][dfa.head] = Ast::Empty;
+        for state in dfa.states.iter() {
+            for tran in state.trans.iter() {
+                if let Tran::Char(ch, to) = tran {
+                    let edge = std::mem::replace(&mut edges[state.index][*to], Ast::Nothing);
+                    edges[state.index][*to] = Ast::alternate(edge, Ast::Char(ch.clone()));
+                }
+            }
+        }
+        for &tail in dfa.tail.iter() {
+            edges[tail][end] = Ast::Empty;
+        }
+
+        let mut left: Vec<usize> = (0..len).collect();
+        while !left.is_empty() {
+            let cost = |k: usize| {
+                let ins = (0..len + 2).filter(|&i| i != k && edges[i][k] != Ast::Nothing).count();
+                let outs = (0..len + 2).filter(|&j| j != k && edges[k][j] != Ast::Nothing).count();
+                ins * outs
+            };
+            let at = (0..left.len()).min_by_key(|&at| cost(left[at])).expect("states are left");
+            let k = left.swap_remove(at);
+
+            let through = Ast::star(edges[k][k].clone());
+            let ins: Vec<usize> = (0..len + 2).filter(|&i| i != k && edges[i][k] != Ast::Nothing).collect();
+            let outs: Vec<usize> = (0..len + 2).filter(|&j| j != k && edges[k][j] != Ast::Nothing).collect();
+            for &i in ins.iter() {
+                for &j in outs.iter() {
+                    let path = Ast::concat(Ast::concat(edges[i][k].clone(), through.clone()), edges[k][j].clone());
+                    let edge = std::mem::replace(&mut edges[i][j], Ast::Nothing);
+                    edges[i][j] = Ast::alternate(edge, path);
+                }
+            }
+            for row in edges.iter_mut() {
+                row[k] = Ast::Nothing;
+            }
+            for edge in edges[k].iter_mut() {
+                *edge = Ast::Nothing;
+            }
+        }
+
+        edges[start][end].to_expression()
+    }
+}
@@ -0,0 +1,200 @@
+use crate::alphabet::*;
+use crate::expression::Expression;
+use crate::glushkov::*;
+use crate::matches::Match;
+use unit::*;
+
+// One position automaton with its positions as the bits of a u64: bit
+// p - 1 stands for position p. A step is two table lookups, the positions
+// reading the char and those following the current ones, and an and.
+struct Bits {
+    first: u64,
+    last: u64,
+    nullable: bool,
+    // follow[k][b]: the positions following those in byte k of the state
+    follow: Vec<[u64; 256]>,
+    ascii: [u64; 128],
+    ranges: Vec<(char, char, u64)>,
+}
+
+impl Bits {
+    fn from_glushkov(glushkov: &Glushkov) -> Option<Bits> {
+        let len = glushkov.chars.len();
+        if len > 64 {
+            return None
+        }
+
+        let mask = |positions: &[usize]| positions.iter().filter(|&&p| p != 0).fold(0u64, |mask, &p| mask | 1 << (p - 1));
+        let last = (1..=len).filter(|&p| glushkov.accepting[p]).fold(0u64, |last, p| last | 1 << (p - 1));
+
+        let mut follow: Vec<[u64; 256]> = vec![[0; 256]; len.div_ceil(8)];
+        for (k, table) in follow.iter_mut().enumerate() {
+            for (b, next) in table.iter_mut().enumerate() {
+                for bit in 0..8 {
+                    let p = k * 8 + bit + 1;
+                    if b & 1 << bit != 0 && p <= len {
+                        *next |= mask(&glushkov.follow[p]);
+                    }
+                }
+            }
+        }
+
+        let reading = |ch: char| (1..=len).filter(|&p| glushkov.chars[p - 1].is_match(ch)).fold(0u64, |mask, p| mask | 1 << (p - 1));
+        let mut ascii = [0u64; 128];
+        for (b, mask) in ascii.iter_mut().enumerate() {
+            *mask = reading(b as u8 as char);
+        }
+
+        let mut ranges: Vec<(char, char, u64)> = Vec::new();
+        for class in classes(&glushkov.get_chars()).iter() {
+            let mask = reading(representative(class));
+            if mask != 0 {
+                ranges.extend(class.to_ranges().iter().filter(|&&(_a, b)| b >= '\u{80}').map(|&(a, b)| (a, b, mask)));
+            }
+        }
+        ranges.sort_by_key(|&(a, _b, _mask)| a);
+
+        Some(Bits {
+            first: mask(&glushkov.follow[0]),
+            last,
+            nullable: glushkov.accepting[0],
+            follow,
+            ascii,
+            ranges,
+        })
+    }
+
+    fn reading(&self, ch: char) -> u64 {
+        if (ch as u32) < 128 {
+            return self.ascii[ch as usize]
+        }
+
+        let i = self.ranges.partition_point(|&(_a, b, _mask)| b < ch);
+        match self.ranges.get(i) {
+            Some(&(a, _b, mask)) if a <= ch => mask,
+            _ => 0,
+        }
+    }
+
+    fn following(&self, state: u64) -> u64 {
+        self.follow.iter().enumerate().fold(0, |next, (k, table)| next | table[(state >> (k * 8)) as usize & 0xff])
+    }
+}
+
+// Simulates the position automaton of a pattern with at most 64 positions
+// without building any dfa. Searches give the leftmost-longest match.
+pub struct BitParallel {
+    forward: Bits,
+    reverse: Bits,
+}
+
+impl BitParallel {
+    pub fn new(pattern: &str) -> Option<BitParallel> {
+        BitParallel::from_expression(&Expression::from_str(&pattern.to_string()).units)
+    }
+
+    // `None` for more than 64 positions, and for an intersection or a
+    // complement, which have no position automaton.
+    pub fn from_expression(expression: &[Unit]) -> Option<BitParallel> {
+        Some(BitParallel {
+            forward: Bits::from_glushkov(&Glushkov::from_expression(expression)?)?,
+            reverse: Bits::from_glushkov(&Glushkov::from_expression_reversed(expression)?)?,
+        })
+    }
+
+    pub fn accepts(&self, content: &str) -> bool {
+        let bits = &self.forward;
+
+        let mut state: u64 = 0;
+        let mut next = bits.first;
+        for ch in content.chars() {
+            state = next & bits.reading(ch);
+            if state == 0 {
+                return false
+            }
+            next = bits.following(state);
+        }
+
+        if content.is_empty() {
+            bits.nullable
+        } else {
+            state & bits.last != 0
+        }
+    }
+
+    pub fn is_match(&self, content: &str) -> bool {
+        let bits = &self.forward;
+        if bits.nullable {
+            return true
+        }
+
+        let mut state: u64 = 0;
+        for ch in content.chars() {
+            state = (bits.following(state) | bits.first) & bits.reading(ch);
+            if state & bits.last != 0 {
+                return true
+            }
+        }
+
+        false
+    }
+
+    pub fn find<'t>(&self, content: &'t str) -> Option<Match<'t>> {
+        self.find_at(content, 0)
+    }
+
+    // A forward scan starts a thread at every char until one accepts, then
+    // runs the threads already started until they die. No match starting
+    // before the first end goes past the last end seen, so a backward scan
+    // from there finds the leftmost start, and an anchored forward scan
+    // from that start the longest end.
+    pub fn find_at<'t>(&self, content: &'t str, at: usize) -> Option<Match<'t>> {
+        let start = if self.forward.nullable {
+            at
+        } else {
+            let bits = &self.forward;
+
+            let mut bound = None;
+            let mut state: u64 = 0;
+            for (i, ch) in content[at..].char_indices() {
+                let first = if bound.is_none() { bits.first } else { 0 };
+                state = (bits.following(state) | first) & bits.reading(ch);
+                if state & bits.last != 0 {
+                    bound = Some(at + i + ch.len_utf8());
+                } else if state == 0 && bound.is_some() {
+                    break;
+                }
+            }
+            let bound = bound?;
+
+            let bits = &self.reverse;
+            let mut start = at;
+            let mut state: u64 = 0;
+            for (i, ch) in content[at..bound].char_indices().rev() {
+                state = (bits.following(state) | bits.first) & bits.reading(ch);
+                if state & bits.last != 0 {
+                    start = at + i;
+                }
+            }
+
+            start
+        };
+
+        let bits = &self.forward;
+        let mut end = if bits.nullable { Some(start) } else { None };
+        let mut state: u64;
+        let mut next = bits.first;
+        for (i, ch) in content[start..].char_indices() {
+            state = next & bits.reading(ch);
+            if state == 0 {
+                break;
+            }
+            if state & bits.last != 0 {
+                end = Some(start + i + ch.len_utf8());
+            }
+            next = bits.following(state);
+        }
+
+        end.map(|end| Match::new(content, start, end))
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::time::Duration;
+use std::time::Instant;
+
+// The deadline and the cancel hook are only looked at once per this many
+// steps, so that checking them costs little next to the search itself.
+const CHECK_EVERY: usize = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SearchError {
+    StepLimit { limit: usize },
+    TimedOut,
+    Cancelled,
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchError::StepLimit { limit } => write!(f, "search exceeds the budget of {} steps", limit),
+            SearchError::TimedOut => write!(f, "search passed its deadline"),
+            SearchError::Cancelled => write!(f, "search was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+// Bounds the work of searches over untrusted input, which give up with an
+// error once it runs out. A step is one char read by a dfa, or one thread
+// moved over one char by the nfa simulations. The steps add up over all
+// the searches a budget is passed to. With nothing set it never runs out.
+pub struct Budget<'a> {
+    limit: Option<usize>,
+    deadline: Option<Instant>,
+    cancel: Option<&'a dyn Fn() -> bool>,
+    steps: usize,
+    next_check: usize,
+}
+
+impl<'a> Budget<'a> {
+    pub fn new() -> Budget<'a> {
+        Budget {
+            limit: None,
+            deadline: None,
+            cancel: None,
+            steps: 0,
+            next_check: 0,
+        }
+    }
+
+    pub fn max_steps(&mut self, limit: usize) -> &mut Budget<'a> {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn deadline(&mut self, deadline: Instant) -> &mut Budget<'a> {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Budget<'a> {
+        self.deadline(Instant::now() + timeout)
+    }
+
+    // The search stops once `cancel` returns true.
+    pub fn cancel_with(&mut self, cancel: &'a dyn Fn() -> bool) -> &mut Budget<'a> {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    fn check(&mut self) -> Result<(), SearchError> {
+        if let Some(limit) = self.limit {
+            if self.steps > limit {
+                return Err(SearchError::StepLimit { limit })
+            }
+        }
+
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(SearchError::TimedOut)
+        }
+        if self.cancel.is_some_and(|cancel| cancel()) {
+            return Err(SearchError::Cancelled)
+        }
+
+        self.next_check = self.steps.saturating_add(CHECK_EVERY);
+        if let Some(limit) = self.limit {
+            self.next_check = self.next_check.min(limit.saturating_add(1));
+        }
+
+        Ok(())
+    }
+}
+
+// What the searches take their steps from. Searches run without a budget
+// take them from `Unlimited`, whose steps compile to nothing.
+pub(crate) trait Steps {
+    fn step(&mut self, steps: usize) -> Result<(), SearchError>;
+}
+
+pub(crate) struct Unlimited;
+
+impl Steps for Unlimited {
+    #[inline]
+    fn step(&mut self, _steps: usize) -> Result<(), SearchError> {
+        Ok(())
+    }
+}
+
+impl Steps for Budget<'_> {
+    // Only adds up the steps until the next check is due, which is kept no
+    // later than the step past the limit.
+    #[inline]
+    fn step(&mut self, steps: usize) -> Result<(), SearchError> {
+        self.steps = self.steps.saturating_add(steps);
+        if self.steps < self.next_check {
+            return Ok(())
+        }
+
+        self.check()
+    }
+}
+
+impl Default for Budget<'_> {
+    fn default() -> Self {
+        Budget::new()
+    }
+}
@@ -0,0 +1,133 @@
+use crate::alphabet::*;
+use crate::ast::*;
+use crate::dfa::*;
+use crate::fastate::*;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use unit::*;
+
+impl Ast {
+    // The expression matching what is left of the strings of `self` that
+    // start with `a`.
+    pub fn derivative(&self, a: char) -> Ast {
+        match self {
+            Ast::Nothing | Ast::Empty => Ast::Nothing,
+            Ast::Char(ch) => {
+                if ch.is_match(a) { Ast::Empty } else { Ast::Nothing }
+            },
+            Ast::Concat(asts) => {
+                let rest = asts[1..].iter().cloned().fold(Ast::Empty, Ast::concat);
+                let head = Ast::concat(asts[0].derivative(a), rest.clone());
+
+                if asts[0].is_nullable() {
+                    Ast::alternate(head, rest.derivative(a))
+                } else {
+                    head
+                }
+            },
+            Ast::Alternate(asts) => asts.iter().map(|ast| ast.derivative(a)).fold(Ast::Nothing, Ast::alternate),
+            Ast::Star(ast) => Ast::concat(ast.derivative(a), self.clone()),
+            Ast::And(asts) => asts.iter().map(|ast| ast.derivative(a)).fold(Ast::complement(Ast::Nothing), Ast::and),
+            Ast::Not(ast) => Ast::complement(ast.derivative(a)),
+        }
+    }
+
+    // Matches the whole of `content` by taking one derivative per char.
+    pub fn is_match(&self, content: &str) -> bool {
+        let mut ast = self.normalize();
+        for ch in content.chars() {
+            if ast == Ast::Nothing {
+                return false
+            }
+            ast = ast.derivative(ch).normalize();
+        }
+
+        ast.is_nullable()
+    }
+
+    pub fn get_chars(&self) -> HashSet<Char> {
+        let mut chars: HashSet<Char> = HashSet::new();
+        let mut stack: Vec<&Ast> = vec![self];
+
+        while let Some(ast) = stack.pop() {
+            match ast {
+                Ast::Char(ch) => {
+                    chars.insert(ch.clone());
+                },
+                Ast::Concat(asts) | Ast::Alternate(asts) | Ast::And(asts) => stack.extend(asts.iter()),
+                Ast::Star(ast) | Ast::Not(ast) => stack.push(ast),
+                Ast::Nothing | Ast::Empty => {},
+            }
+        }
+
+        chars
+    }
+}
+
+impl Dfa {
+    // Builds a dfa whose states are the normalized derivatives of `ast`. All
+    // chars of a class give the same derivative, so one char per class is
+    // enough. The derivative that matches nothing is left out as the dead
+    // state.
+    pub fn from_ast(ast: &Ast) -> Dfa {
+        let classes = classes(&ast.get_chars());
+        let start = ast.normalize();
+
+        let mut dfa = Dfa {
+            head: 0,
+            tail: vec![],
+            states: Vec::new(),
+        };
+        let mut raw_states: Vec<Ast> = Vec::new();
+        let mut map: HashMap<Ast, usize> = HashMap::new();
+
+        let add_state = |dfa: &mut Dfa, raw_states: &mut Vec<Ast>, map: &mut HashMap<Ast, usize>, ast: Ast| {
+            let id = dfa.states.len();
+            let kind = if ast.is_nullable() {
+                dfa.tail.push(id);
+                FaStateType::Tail
+            } else if id == 0 {
+                FaStateType::Head
+            } else {
+                FaStateType::Normal
+            };
+
+            dfa.states.push(FaState {
+                index: id,
+                trans: HashSet::new(),
+                kind,
+            });
+            map.insert(ast.clone(), id);
+            raw_states.push(ast);
+
+            id
+        };
+        add_state(&mut dfa, &mut raw_states, &mut map, start);
+
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        queue.push_back(0);
+
+        while let Some(t) = queue.pop_front() {
+            for class in classes.iter() {
+                let u = raw_states[t].derivative(representative(class)).normalize();
+                if u == Ast::Nothing {
+                    continue;
+                }
+
+                let u_id = match map.get(&u) {
+                    Some(&u_id) => u_id,
+                    None => {
+                        let u_id = add_state(&mut dfa, &mut raw_states, &mut map, u);
+                        queue.push_back(u_id);
+                        u_id
+                    },
+                };
+
+                dfa.states[t].add_char_tran(class.clone(), u_id);
+            }
+        }
+
+        dfa
+    }
+}
@@ -1,7 +1,37 @@
+use crate::alphabet::*;
 use crate::fastate::*;
+use crate::nfa::*;
+use crate::error::BuildError;
+use crate::serialize::*;
+use crate::table::*;
 use unit::*;
 use std::collections::HashSet;
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MatchKind {
+    LeftmostFirst,
+    LeftmostLongest,
+    Earliest,
+}
+
+// Names a state of a dfa for callers driving it one char at a time. The
+// dead state stands for every missing tran: it never accepts and never
+// leaves, so a caller can stop feeding chars once it gets there.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct StateId(pub(crate) usize);
+
+impl StateId {
+    pub const DEAD: StateId = StateId(usize::MAX);
+
+    pub fn index(self) -> Option<usize> {
+        if self == StateId::DEAD {
+            None
+        } else {
+            Some(self.0)
+        }
+    }
+}
+
 pub struct Dfa {
     pub head: usize,
     pub tail: Vec<usize>,
@@ -11,7 +41,7 @@ pub struct Dfa {
 use std::collections::VecDeque;
 use std::collections::HashMap;
 
-fn get_epsilon_closure(t: Option<Vec<bool>>, states: &Vec<FaState>) -> Option<Vec<bool>> {
+fn get_epsilon_closure(t: Option<Vec<bool>>, states: &[FaState]) -> Option<Vec<bool>> {
     match t {
         None => None,
         Some(t) => {
@@ -42,14 +72,95 @@ fn get_epsilon_closure(t: Option<Vec<bool>>, states: &Vec<FaState>) -> Option<Ve
         }
     }
 }
-fn get_move(t: Vec<bool>, a: &Char, states: &Vec<FaState>) -> Option<Vec<bool>> {
+// Puts a group of threads in canonical order and tells whether one of them
+// accepts. A leftmost-first group keeps its priority order and loses the
+// threads behind the accepting one.
+fn accept_group<T: Threads + ?Sized>(group: &mut Vec<usize>, threads: &T, kind: MatchKind) -> bool {
+    let position = group.iter().position(|&i| threads.is_final(i));
+
+    match kind {
+        MatchKind::LeftmostFirst => {
+            if let Some(k) = position {
+                group.truncate(k + 1);
+            }
+        },
+        _ => group.sort(),
+    }
+
+    position.is_some()
+}
+
+// Collects the states reachable from `state` by epsilon trans in priority
+// order. The nfa constructions always number the preferred branch of an
+// alternation or a greedy repeat lower, so lower targets are visited first.
+fn get_ordered_closure(state: usize, states: &[FaState], seen: &mut [bool], closure: &mut Vec<usize>) {
+    let mut stack: Vec<usize> = vec![state];
+
+    while let Some(t) = stack.pop() {
+        if seen[t] {
+            continue;
+        }
+        seen[t] = true;
+        closure.push(t);
+
+        let mut targets: Vec<usize> = states[t].trans.iter().filter_map(|tran| match tran {
+            Tran::Epsilon(to) if !seen[*to] => Some(*to),
+            _ => None,
+        }).collect();
+        targets.sort();
+
+        stack.extend(targets.iter().rev());
+    }
+}
+
+// The threads of an automaton as the grouped construction runs them. Both
+// lists come in priority order, leaving out the threads `seen` marks as
+// already reached by a thread of higher priority.
+pub(crate) trait Threads {
+    fn state_count(&self) -> usize;
+    fn is_final(&self, i: usize) -> bool;
+    fn start(&self, seen: &mut [bool], threads: &mut Vec<usize>);
+    fn step(&self, i: usize, ch: char, seen: &mut [bool], threads: &mut Vec<usize>);
+}
+
+impl Threads for [FaState] {
+    fn state_count(&self) -> usize {
+        self.len()
+    }
+
+    fn is_final(&self, i: usize) -> bool {
+        is_final(self, i)
+    }
+
+    fn start(&self, seen: &mut [bool], threads: &mut Vec<usize>) {
+        get_ordered_closure(0, self, seen, threads);
+    }
+
+    fn step(&self, i: usize, ch: char, seen: &mut [bool], threads: &mut Vec<usize>) {
+        let mut targets: Vec<usize> = self[i].trans.iter().filter_map(|tran| match tran {
+            Tran::Char(ch_, to) if ch_.is_match(ch) => Some(*to),
+            _ => None,
+        }).collect();
+        targets.sort();
+
+        for to in targets {
+            get_ordered_closure(to, self, seen, threads);
+        }
+    }
+}
+
+fn accepts_any(set: &[bool], states: &[FaState]) -> bool {
+    set.iter().enumerate().any(|(i, &b)| b && is_final(states, i))
+}
+
+fn get_move(t: Vec<bool>, a: char, states: &[FaState]) -> Option<Vec<bool>> {
     let mut m_t = None;
 
     for (i, &b) in t.iter().enumerate() {
         if b == true {
             for tran in &states[i].trans {
                 if let Tran::Char(ch, to) = tran {
-                    if *ch == *a {
+                    if ch.is_match(a) {
                         if let None = m_t {
                             let mut tmp: Vec<bool> = Vec::new();
                             for _i in 0..states.len() {
@@ -76,6 +187,12 @@ fn get_move(t: Vec<bool>, a: &Char, states: &Vec<FaState>) -> Option<Vec<bool>>
 
 impl Dfa {
     pub fn from_nfa(nfa_states: &Vec<FaState>, chars: HashSet<Char>) -> Dfa {
+        Dfa::from_nfa_limited(nfa_states, chars, usize::MAX).expect("Failed to build dfa")
+    }
+
+    // Fails instead of adding a state past the first `limit` ones.
+    pub(crate) fn from_nfa_limited(nfa_states: &[FaState], chars: HashSet<Char>, limit: usize) -> Result<Dfa, BuildError> {
+        let classes = classes(&chars);
         let mut dfa = Dfa {
             head: 0,
             tail: vec![],
@@ -90,42 +207,46 @@ impl Dfa {
         let mut bool_states: Vec<bool> = Vec::new();
         for _i in 0..nfa_states.len() { bool_states.push(false); }
         bool_states[0] = true;
-        bool_states = match get_epsilon_closure(Some(bool_states), &nfa_states) {
+        bool_states = match get_epsilon_closure(Some(bool_states), nfa_states) {
             None => panic!(""),
             Some(bool_states) => bool_states,
         };
 
+        if accepts_any(&bool_states, nfa_states) {
+            dfa.states[0].kind = FaStateType::Tail;
+            dfa.tail.push(0);
+        }
+
         let mut raw_states: Vec<Vec<bool>> = Vec::new();
         raw_states.push(bool_states.clone());
 
         let mut map: HashMap<Vec<bool>, usize> = HashMap::new();
         map.insert(bool_states.clone(), 0);
 
-        let mut done: HashSet<usize> = HashSet::new();
-
-
+        // a state is queued once, when it is first reached
         let mut queue: VecDeque<usize> = VecDeque::new();
         queue.push_back(0);
 
         loop {
 
             if let Some(t) = queue.pop_front() {
-                done.insert(t);
-
-                for ch in chars.iter() {
+                for class in classes.iter() {
                     let bool_states = raw_states[t].clone();
 
-                    if let Some(u) = get_epsilon_closure(get_move(bool_states, ch, &nfa_states), &nfa_states) {
+                    if let Some(u) = get_epsilon_closure(get_move(bool_states, representative(class), nfa_states), nfa_states) {
                         let u_id = if let Some(u_id) = map.get(&u) {
                             *u_id
                         } else {
                             let u_id = dfa.states.len();
+                            if u_id >= limit {
+                                return Err(BuildError::DfaTooBig { limit })
+                            }
 
                             dfa.states.push(FaState {
                                 index: u_id,
                                 trans: [].iter().cloned().collect(),
                                 kind: {
-                                    if u[nfa_states.len() - 1] {
+                                    if accepts_any(&u, nfa_states) {
                                         dfa.tail.push(u_id);
                                         FaStateType::Tail
                                     } else {
@@ -137,21 +258,256 @@ impl Dfa {
                             raw_states.push(u.clone());
 
                             map.insert(u.clone(), u_id);
+                            queue.push_back(u_id);
 
                             u_id
                         };
 
-                        dfa.states[t].add_char_tran(ch.clone(), u_id);
-
-                        if let None = done.get(&u_id) {
-                            queue.push_back(u_id);
-                        }
+                        dfa.states[t].add_char_tran(class.clone(), u_id);
                     }
                 }
             } else {break;}
         }
 
-        dfa
+        Ok(dfa)
+    }
+
+    pub fn from_nfa_unanchored(nfa_states: &Vec<FaState>, chars: HashSet<Char>, kind: MatchKind, allow_empty: bool) -> Dfa {
+        Dfa::from_threads(nfa_states.as_slice(), chars, kind, false, allow_empty, usize::MAX).expect("Failed to build dfa")
+    }
+
+    pub fn from_nfa_anchored(nfa_states: &Vec<FaState>, chars: HashSet<Char>, kind: MatchKind, allow_empty: bool) -> Dfa {
+        Dfa::from_threads(nfa_states.as_slice(), chars, kind, true, allow_empty, usize::MAX).expect("Failed to build dfa")
+    }
+
+    // Builds a dfa that finds the end of the leftmost match in one scan. A
+    // state keeps the threads grouped by the position they started at,
+    // earliest first. Once a group accepts, the groups started after it
+    // are dropped and no new thread is started, so the last
+    // accepting position seen before the dfa dies is the end of the match.
+    //
+    // For leftmost-first the threads of a group are also kept in priority
+    // order, and an accepting thread drops the threads behind it. Unless
+    // `allow_empty` is set, a thread has to read a char before it accepts.
+    // More than `limit` states fail the construction.
+    pub(crate) fn from_threads<T: Threads + ?Sized>(threads: &T, chars: HashSet<Char>, kind: MatchKind, anchored: bool, allow_empty: bool, limit: usize) -> Result<Dfa, BuildError> {
+        let classes = classes(&chars);
+        let len = threads.state_count();
+
+        let mut dfa = Dfa {
+            head: 0,
+            tail: vec![],
+            states: vec![FaState {
+                index: 0,
+                trans: HashSet::new(),
+                kind: FaStateType::Head,
+            }],
+        };
+
+        let mut seen: Vec<bool> = vec![false; len];
+        let mut start: Vec<usize> = Vec::new();
+        threads.start(&mut seen, &mut start);
+        if kind != MatchKind::LeftmostFirst {
+            start.sort();
+        }
+
+        let mut head_group = start.clone();
+        let head_accepted = allow_empty && accept_group(&mut head_group, threads, kind);
+        if head_accepted {
+            dfa.states[0].kind = FaStateType::Tail;
+            dfa.tail.push(0);
+        }
+
+        let mut raw_states: Vec<(Vec<Vec<usize>>, bool)> = vec![(vec![head_group], head_accepted)];
+
+        let mut map: HashMap<(Vec<Vec<usize>>, bool), usize> = HashMap::new();
+        map.insert(raw_states[0].clone(), 0);
+
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        queue.push_back(0);
+
+        while let Some(t) = queue.pop_front() {
+            for class in classes.iter() {
+                let (groups, matched) = raw_states[t].clone();
+                let ch = representative(class);
+
+                let mut seen: Vec<bool> = vec![false; len];
+                let mut new_groups: Vec<Vec<usize>> = Vec::new();
+                let mut accepted = false;
+
+                for group in groups.iter() {
+                    let mut new_group: Vec<usize> = Vec::new();
+                    for &i in group.iter() {
+                        threads.step(i, ch, &mut seen, &mut new_group);
+                    }
+
+                    if accept_group(&mut new_group, threads, kind) {
+                        new_groups.push(new_group);
+                        accepted = true;
+                        break;
+                    }
+
+                    if !new_group.is_empty() {
+                        new_groups.push(new_group);
+                    }
+                }
+
+                if !matched && !accepted && !anchored {
+                    let mut group: Vec<usize> = start.iter().cloned().filter(|&i| !seen[i]).collect();
+                    if allow_empty && accept_group(&mut group, threads, kind) {
+                        accepted = true;
+                    }
+                    if !group.is_empty() {
+                        new_groups.push(group);
+                    }
+                }
+                let matched = matched || accepted;
+
+                if new_groups.is_empty() {
+                    continue;
+                }
+
+                let u = (new_groups, matched);
+                let u_id = if let Some(u_id) = map.get(&u) {
+                    *u_id
+                } else {
+                    let u_id = dfa.states.len();
+                    if u_id >= limit {
+                        return Err(BuildError::DfaTooBig { limit })
+                    }
+
+                    dfa.states.push(FaState {
+                        index: u_id,
+                        trans: HashSet::new(),
+                        kind: {
+                            if accepted {
+                                dfa.tail.push(u_id);
+                                FaStateType::Tail
+                            } else {
+                                FaStateType::Normal
+                            }
+                        },
+                    });
+
+                    raw_states.push(u.clone());
+                    map.insert(u, u_id);
+                    queue.push_back(u_id);
+
+                    u_id
+                };
+
+                dfa.states[t].add_char_tran(class.clone(), u_id);
+            }
+        }
+
+        Ok(dfa)
+    }
+
+    pub fn get_chars(&self) -> HashSet<Char> {
+        let mut ans: HashSet<Char> = HashSet::new();
+        for state in self.states.iter() {
+            for tran in state.trans.iter() {
+                if let Tran::Char(ch, _to) = tran {
+                    ans.insert(ch.clone());
+                }
+            }
+        }
+
+        ans
+    }
+
+    pub(crate) fn step(&self, cur_state: usize, ch: char) -> Option<usize> {
+        for tran in self.states[cur_state].trans.iter() {
+            match tran {
+                Tran::Char(ch_, to) => {
+                    if ch_.is_match(ch) {
+                        return Some(*to)
+                    }
+                },
+                Tran::Epsilon(_to) => panic!("epsilon tran should not be found in dfa"),
+            }
+        }
+
+        None
+    }
+
+    pub fn start_state(&self) -> StateId {
+        StateId(self.head)
+    }
+
+    pub fn next_state(&self, state: StateId, ch: char) -> StateId {
+        match state.index() {
+            Some(index) => self.step(index, ch).map_or(StateId::DEAD, StateId),
+            None => StateId::DEAD,
+        }
+    }
+
+    pub fn is_match_state(&self, state: StateId) -> bool {
+        state.index().is_some_and(|index| self.is_accepted(index))
+    }
+
+    pub fn is_dead_state(&self, state: StateId) -> bool {
+        state == StateId::DEAD
+    }
+
+    pub fn is_accepted(&self, state: usize) -> bool {
+        matches!(self.states[state].kind, FaStateType::Tail)
+    }
+
+    pub fn accepts(&self, content: &str) -> bool {
+        let mut cur_state = self.head;
+        for ch in content.chars() {
+            match self.step(cur_state, ch) {
+                Some(new_state) => cur_state = new_state,
+                None => return false,
+            }
+        }
+
+        self.is_accepted(cur_state)
+    }
+
+    // Reversing a dfa gives one start per accepting state, so the reversed
+    // nfa gets a fresh head with an epsilon to each of them, and the old head
+    // leads to a fresh tail.
+    pub fn reverse(&self) -> Dfa {
+        let len = self.states.len() + 2;
+
+        let mut states: Vec<FaState> = (0..len).map(|i| FaState {
+            index: i,
+            trans: HashSet::new(),
+            kind: FaStateType::Normal,
+        }).collect();
+
+        for &tail in self.tail.iter() {
+            states[0].add_epsilon_tran(tail + 1);
+        }
+        states[self.head + 1].add_epsilon_tran(len - 1);
+
+        for state in self.states.iter() {
+            for tran in state.trans.iter() {
+                if let Tran::Char(ch, to) = tran {
+                    states[to + 1].add_char_tran(ch.clone(), state.index + 1);
+                }
+            }
+        }
+
+        Dfa::from_nfa(&states, self.get_chars())
+    }
+
+    // Serializes the dfa as its dense table, see `serialize` for the format.
+    // The bytes can be embedded with `include_bytes!` and used in place with
+    // `DfaRef::from_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.try_to_bytes().expect("Failed to serialize dfa")
+    }
+
+    // Fails when a count or state index of the dfa does not fit in a u32.
+    pub fn try_to_bytes(&self) -> Result<Vec<u8>, SerializeError> {
+        encode(&DenseDfa::from_dfa(self))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Dfa, DeserializeError> {
+        DfaRef::from_bytes(bytes).map(|dfa| dfa.to_dfa())
     }
 
     pub fn to_string(&self) -> String {
@@ -0,0 +1,174 @@
+use crate::dfa::*;
+use crate::fastate::*;
+use unit::*;
+
+// Number of chars in `a..=b`, surrogates left out.
+pub(crate) fn range_len(a: char, b: char) -> u64 {
+    let (a, b) = (a as u64, b as u64);
+    let (lo, hi) = (a.max(0xD800), b.min(0xDFFF));
+    let surrogates = if lo <= hi { hi - lo + 1 } else { 0 };
+
+    b - a + 1 - surrogates
+}
+
+impl Dfa {
+    // The trans of every state as char ranges sorted by their first char.
+    pub(crate) fn sorted_ranges(&self) -> Vec<Vec<(char, char, usize)>> {
+        self.states.iter().map(|state| {
+            let mut ranges: Vec<(char, char, usize)> = Vec::new();
+            for tran in state.trans.iter() {
+                if let Tran::Char(ch, to) = tran {
+                    ranges.extend(ch.to_ranges().iter().map(|&(a, b)| (a, b, *to)));
+                }
+            }
+            ranges.sort();
+
+            ranges
+        }).collect()
+    }
+
+    // `ways[k][s]` is the number of strings of length `k` leading from state
+    // `s` to an accepting state, saturating at `u64::MAX`.
+    pub(crate) fn count_paths(&self, max_len: usize) -> Vec<Vec<u64>> {
+        let ranges = self.sorted_ranges();
+        let mut ways: Vec<Vec<u64>> = vec![(0..self.states.len()).map(|i| self.is_accepted(i) as u64).collect()];
+
+        for k in 1..=max_len {
+            let row = ranges.iter().map(|ranges| {
+                ranges.iter().fold(0u64, |sum, &(a, b, to)| {
+                    sum.saturating_add(range_len(a, b).saturating_mul(ways[k - 1][to]))
+                })
+            }).collect();
+            ways.push(row);
+        }
+
+        ways
+    }
+
+    // How many strings of each length from 0 to `max_len` are accepted.
+    pub fn count_strings(&self, max_len: usize) -> Vec<u64> {
+        self.count_paths(max_len).iter().map(|row| row[self.head]).collect()
+    }
+
+    pub fn iter_strings(&self) -> Strings<'_> {
+        Strings::new(self)
+    }
+}
+
+// Yields the accepted strings in shortlex order: by length, then by chars.
+// Only states from which an accepted string of the remaining length starts
+// are entered, so every step of the walk leads to a string.
+pub struct Strings<'d> {
+    dfa: &'d Dfa,
+    ranges: Vec<Vec<(char, char, usize)>>,
+    alive: Vec<Vec<bool>>,
+    max_len: Option<usize>,
+    len: usize,
+    path: Vec<(usize, usize, char)>,
+    started: bool,
+}
+
+impl<'d> Strings<'d> {
+    pub fn new(dfa: &'d Dfa) -> Strings<'d> {
+        let max_len = if dfa.is_finite() {
+            dfa.longest_match_length()
+        } else {
+            Some(usize::MAX)
+        };
+
+        Strings {
+            dfa,
+            ranges: dfa.sorted_ranges(),
+            alive: vec![(0..dfa.states.len()).map(|i| dfa.is_accepted(i)).collect()],
+            max_len,
+            len: 0,
+            path: Vec::new(),
+            started: false,
+        }
+    }
+
+    fn is_alive(&mut self, state: usize, len: usize) -> bool {
+        while self.alive.len() <= len {
+            let last = &self.alive[self.alive.len() - 1];
+            let row = self.ranges.iter()
+                .map(|ranges| ranges.iter().any(|&(_a, _b, to)| last[to]))
+                .collect();
+            self.alive.push(row);
+        }
+
+        self.alive[len][state]
+    }
+
+    // Extends the path with the smallest choices from `state` until it
+    // spells a string of `self.len` chars.
+    fn descend(&mut self, mut state: usize) {
+        while self.path.len() < self.len {
+            let rest = self.len - self.path.len() - 1;
+            let i = (0..self.ranges[state].len())
+                .find(|&i| {
+                    let to = self.ranges[state][i].2;
+                    self.is_alive(to, rest)
+                })
+                .expect("an alive state has an alive target");
+
+            let (a, _b, to) = self.ranges[state][i];
+            self.path.push((state, i, a));
+            state = to;
+        }
+    }
+
+    // Moves to the next string of the same length, or returns false.
+    fn advance(&mut self) -> bool {
+        while let Some((state, i, ch)) = self.path.pop() {
+            let rest = self.len - self.path.len() - 1;
+            let (_a, b, to) = self.ranges[state][i];
+
+            if let Some(next) = next_char(ch).filter(|&next| next <= b) {
+                self.path.push((state, i, next));
+                self.descend(to);
+                return true
+            }
+
+            let next_range = (i + 1..self.ranges[state].len()).find(|&j| {
+                let to = self.ranges[state][j].2;
+                self.is_alive(to, rest)
+            });
+            if let Some(j) = next_range {
+                let (a, _b, to) = self.ranges[state][j];
+                self.path.push((state, j, a));
+                self.descend(to);
+                return true
+            }
+        }
+
+        false
+    }
+}
+
+impl<'d> Iterator for Strings<'d> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let max_len = self.max_len?;
+
+        if !self.started || !self.advance() {
+            if self.started {
+                self.len += 1;
+            }
+            self.started = true;
+
+            while !self.is_alive(self.dfa.head, self.len) {
+                if self.len >= max_len {
+                    self.max_len = None;
+                    return None
+                }
+                self.len += 1;
+            }
+
+            self.path.clear();
+            self.descend(self.dfa.head);
+        }
+
+        Some(self.path.iter().map(|&(_state, _i, ch)| ch).collect())
+    }
+}
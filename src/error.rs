@@ -0,0 +1,24 @@
+use std::fmt;
+
+// Why a regex was not built. The nfa limit counts the states of the
+// thompson nfa while it is built, and the positions and follow entries of
+// the glushkov automaton. The dfa limit counts the states of each dfa.
+// A syntax error is at a byte offset of the pattern.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BuildError {
+    NfaTooBig { limit: usize },
+    DfaTooBig { limit: usize },
+    Syntax { at: usize, message: &'static str },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::NfaTooBig { limit } => write!(f, "nfa exceeds the size limit of {} states", limit),
+            BuildError::DfaTooBig { limit } => write!(f, "dfa exceeds the size limit of {} states", limit),
+            BuildError::Syntax { at, message } => write!(f, "syntax error at {}: {}", at, message),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -1,5 +1,11 @@
+use crate::error::BuildError;
 use unit::*;
 use std::collections::HashSet;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+// Byte offsets of the text a unit was read from, end exclusive.
+pub type Span = (usize, usize);
 
 pub struct Expression {
     pub units: Vec<Unit>,
@@ -18,64 +24,107 @@ impl Expression {
     }
 
     pub fn str_to_inffix_exp(regex: &String) -> Vec<Unit> {
-        let mut units: Vec<Unit> = Vec::new();
+        Expression::str_to_spanned_inffix(regex)
+            .expect("Failed to parse pattern")
+            .into_iter()
+            .map(|(u, _span)| u)
+            .collect()
+    }
+
+    fn str_to_spanned_inffix(regex: &str) -> Result<Vec<(Unit, Span)>, BuildError> {
+        let mut units: Vec<(Unit, Span)> = Vec::new();
         let mut stack = String::new();
+        let mut stack_start: usize = 0;
         let mut count: u32 = 0;
 
         let mut escape_mode: bool = false;
-        let mut escape_str = String::new();
+        let mut escape_start: usize = 0;
 
-        for ch in regex.chars() {
+        let mut chars = regex.char_indices().peekable();
+        while let Some((pos, ch)) = chars.next() {
             if escape_mode {
-                escape_str.push(ch);
-
+                // an escaped char is read as itself, `\&` and `\~` included
+                escape_mode = false;
+                units.push((Unit::Char(Char::Single(ch)), (escape_start, pos + ch.len_utf8())));
             } else {
                 match ch {
-                    '[' |
+                    // inside a set, `[` only opens the subtracted set of
+                    // `[a-z-[aeiou]]`, and `{` and `}` are plain chars
+                    '[' => {
+                        if count == 0 {
+                            stack_start = pos;
+                            count += 1;
+                        } else if Expression::opens_subtraction(&stack) {
+                            count += 1;
+                        }
+                        stack.push(ch);
+                    },
                     '{' => {
-                        count += 1;
+                        if count == 0 {
+                            stack_start = pos;
+                            count += 1;
+                        }
                         stack.push(ch);
                     },
                     ']' => {
+                        if count == 0 {
+                            return Err(BuildError::Syntax { at: pos, message: "unmatched ]" })
+                        }
                         count -= 1;
                         stack.push(ch);
 
                         if count == 0 {
-                            units.push(match Char::from_str(&stack) {
+                            units.push((match Char::from_str(&stack) {
                                 Some(ch) => Unit::Char(ch),
-                                None => panic!("error"),
-                            });
+                                None => return Err(BuildError::Syntax { at: stack_start, message: "invalid set" }),
+                            }, (stack_start, pos + 1)));
                             stack.clear();
                         }
                     },
+                    '}' if stack.starts_with('[') => {
+                        stack.push(ch);
+                    },
                     '}' => {
+                        if count == 0 {
+                            return Err(BuildError::Syntax { at: pos, message: "unmatched }" })
+                        }
                         count -= 1;
                         stack.push(ch);
 
                         if count == 0 {
-                            units.push(match Repeat::from_str(&stack) {
+                            units.push((match Repeat::from_str(&stack) {
+                                Some(Repeat::FromTo(m, n)) if m > n => {
+                                    return Err(BuildError::Syntax { at: stack_start, message: "invalid repeat" })
+                                },
                                 Some(rp) => Unit::Operator(Operator::Repeat(rp)),
-                                None => panic!("error"),
-                            });
+                                None => return Err(BuildError::Syntax { at: stack_start, message: "invalid repeat" }),
+                            }, (stack_start, pos + 1)));
                             stack.clear();
                         }
                     },
                     '\\' => {
-                        escape_mode = true;
+                        if count == 0 {
+                            escape_mode = true;
+                            escape_start = pos;
+                        } else {
+                            stack.push(ch);
+                        }
                     },
                     _   => {
                         if count == 0 {
-                            units.push(
-                                match ch {
-                                    '(' => Unit::Operator(Operator::LeftParenthese()),
-                                    ')' => Unit::Operator(Operator::RightParenthese()),
-                                    '|' => Unit::Operator(Operator::Alternation()),
-                                    '*' => Unit::Operator(Operator::Repeat(Repeat::FromZero())),
-                                    '+' => Unit::Operator(Operator::Repeat(Repeat::From(1))),
-                                    '?' => Unit::Operator(Operator::Repeat(Repeat::Maybe())),
-                                    _   => Unit::Char(Char::Single(ch)),
-                                }
-                            );
+                            let unit = match ch {
+                                '(' => Unit::Operator(Operator::LeftParenthese(Expression::take_group_name(&mut chars))),
+                                ')' => Unit::Operator(Operator::RightParenthese()),
+                                '|' => Unit::Operator(Operator::Alternation()),
+                                '&' => Unit::Operator(Operator::Intersection()),
+                                '~' => Unit::Operator(Operator::Complement()),
+                                '*' => Unit::Operator(Operator::Repeat(Repeat::FromZero())),
+                                '+' => Unit::Operator(Operator::Repeat(Repeat::From(1))),
+                                '?' => Unit::Operator(Operator::Repeat(Repeat::Maybe())),
+                                _   => Unit::Char(Char::Single(ch)),
+                            };
+                            let end = chars.peek().map_or(regex.len(), |&(next, _ch)| next);
+                            units.push((unit, (pos, end)));
                         } else {
                             stack.push(ch);
                         }
@@ -85,10 +134,20 @@ impl Expression {
 
         }
 
+        if count > 0 {
+            let message = if stack.starts_with('[') { "unclosed set" } else { "unclosed repeat" };
+            return Err(BuildError::Syntax { at: stack_start, message })
+        }
+        if escape_mode {
+            return Err(BuildError::Syntax { at: escape_start, message: "trailing backslash" })
+        }
+
+        // a concatenation gets an empty span where the unit after it starts
         let tmp =  units;
-        let mut units: Vec<Unit> = Vec::new();
-        for u in tmp.iter() {
-            if let Some(top) = units.last() {
+        let mut units: Vec<(Unit, Span)> = Vec::new();
+        for (u, span) in tmp.iter() {
+            let concat = (Unit::Operator(Operator::Concatenation()), (span.0, span.0));
+            if let Some((top, _top_span)) = units.last() {
                 match top {
                     Unit::Operator(t_op) => {
                         match t_op {
@@ -96,14 +155,15 @@ impl Expression {
                                 match u {
                                     Unit::Operator(u_op) => {
                                         match u_op {
-                                            Operator::LeftParenthese() => {
-                                                units.push(Unit::Operator(Operator::Concatenation()));
+                                            Operator::LeftParenthese(_) |
+                                            Operator::Complement() => {
+                                                units.push(concat.clone());
                                             },
                                             _ => {},
                                         }
                                     },
                                     Unit::Char(_u_ch) => {
-                                        units.push(Unit::Operator(Operator::Concatenation()));
+                                        units.push(concat.clone());
                                     }
                                 }
                             },
@@ -111,14 +171,15 @@ impl Expression {
                                 match u {
                                     Unit::Operator(u_op) => {
                                         match u_op {
-                                            Operator::LeftParenthese() => {
-                                                units.push(Unit::Operator(Operator::Concatenation()));
+                                            Operator::LeftParenthese(_) |
+                                            Operator::Complement() => {
+                                                units.push(concat.clone());
                                             },
                                             _ => {},
                                         }
                                     },
                                     Unit::Char(_u_ch) => {
-                                        units.push(Unit::Operator(Operator::Concatenation()));
+                                        units.push(concat.clone());
                                     }
                                 }
                             }
@@ -129,47 +190,124 @@ impl Expression {
                         match u {
                             Unit::Operator(u_op) => {
                                 match u_op {
-                                    Operator::LeftParenthese() => {
-                                        units.push(Unit::Operator(Operator::Concatenation()));
+                                    Operator::LeftParenthese(_) |
+                                    Operator::Complement() => {
+                                        units.push(concat.clone());
                                     },
                                     _ => {},
                                 }
                             },
                             Unit::Char(_u_ch) => {
-                                units.push(Unit::Operator(Operator::Concatenation()));
+                                units.push(concat.clone());
                             },
                         }
                     }
                 }
             }
 
-            units.push(u.clone());
+            units.push((u.clone(), *span));
         }
 
-        units
+        Expression::check_operands(&units, regex.len())?;
+
+        Ok(units)
+    }
+
+    // Checks that every operator has its operands and that parentheses are
+    // balanced, which the conversion to postfix takes for granted.
+    fn check_operands(units: &[(Unit, Span)], len: usize) -> Result<(), BuildError> {
+        // whether the units so far end with an operand
+        let mut operand = false;
+        let mut open: Vec<usize> = Vec::new();
+
+        for (i, (u, span)) in units.iter().enumerate() {
+            let missing = BuildError::Syntax { at: span.0, message: "missing operand" };
+            match u {
+                Unit::Char(_) => operand = true,
+                Unit::Operator(op) => match op {
+                    Operator::LeftParenthese(_) => {
+                        open.push(span.0);
+                        operand = false;
+                    },
+                    Operator::RightParenthese() => {
+                        if open.pop().is_none() {
+                            return Err(BuildError::Syntax { at: span.0, message: "unmatched )" })
+                        }
+                        if i > 0 && matches!(units[i - 1].0, Unit::Operator(Operator::LeftParenthese(_))) {
+                            return Err(BuildError::Syntax { at: units[i - 1].1.0, message: "empty group" })
+                        }
+                        if !operand {
+                            return Err(missing)
+                        }
+                    },
+                    Operator::Repeat(_) => {
+                        if !operand {
+                            return Err(BuildError::Syntax { at: span.0, message: "repeat without an operand" })
+                        }
+                    },
+                    Operator::Complement() => operand = false,
+                    Operator::Alternation() |
+                    Operator::Intersection() |
+                    Operator::Concatenation() => {
+                        if !operand {
+                            return Err(missing)
+                        }
+                        operand = false;
+                    },
+                    Operator::Capture(_, _) => {},
+                },
+            }
+        }
+
+        if let Some(&at) = open.last() {
+            return Err(BuildError::Syntax { at, message: "unclosed (" })
+        }
+        if !operand {
+            return Err(BuildError::Syntax { at: len, message: "missing operand" })
+        }
+
+        Ok(())
     }
 
     pub fn inffix_to_suffix(regex: Vec<Unit>) -> Vec<Unit> {
-        let mut ans: Vec<Unit> = Vec::new();
-        let mut stack: Vec<Unit> = Vec::new();
+        let regex = regex.into_iter().map(|u| (u, (0, 0))).collect();
+        Expression::spanned_inffix_to_suffix(regex).into_iter().map(|(u, _span)| u).collect()
+    }
+
+    fn spanned_inffix_to_suffix(regex: Vec<(Unit, Span)>) -> Vec<(Unit, Span)> {
+        let mut ans: Vec<(Unit, Span)> = Vec::new();
+        let mut stack: Vec<(Unit, Span)> = Vec::new();
 
         let mut back_reference_stack: Vec<Vec<Vec<Unit>>> = Vec::new();
         // let mut back_reference_point: usize = 0;
 
-        for u in regex.iter() {
+        let mut group_count: usize = 0;
+        let mut groups: Vec<usize> = Vec::new();
+
+        for (u, span) in regex.iter() {
+            if let Unit::Operator(Operator::LeftParenthese(_name)) = u {
+                group_count += 1;
+                groups.push(group_count);
+            }
+
             let pivot: u32 = u.get_pivot();
             match pivot {
-                0 => ans.push(u.clone()),
+                0 => ans.push((u.clone(), *span)),
                 _ => {
                     if stack.is_empty() {
-                        stack.push(u.clone());
-                    } else if *u == Unit::Operator(Operator::LeftParenthese()) {
-                        stack.push(u.clone());
+                        stack.push((u.clone(), *span));
+                    } else if let Unit::Operator(Operator::LeftParenthese(_name)) = u {
+                        stack.push((u.clone(), *span));
                     } else if *u == Unit::Operator(Operator::RightParenthese()) {
+                        // a capture spans from its opening to its closing parenthese
                         loop {
                             ans.push(if let Some(unit) = stack.pop() {
                                 match unit {
-                                    Unit::Operator(Operator::LeftParenthese()) => break,
+                                    (Unit::Operator(Operator::LeftParenthese(name)), left) => {
+                                        let index = groups.pop().expect("Unbalanced parentheses");
+                                        ans.push((Unit::Operator(Operator::Capture(index, name)), (left.0, span.1)));
+                                        break;
+                                    },
                                     _ => unit,
                                 }
                             } else { break; });
@@ -179,7 +317,7 @@ impl Expression {
                     } else {
                         loop {
                             let stk_pivot: u32 = {
-                                if let Some(unit) = stack.last() {
+                                if let Some((unit, _span)) = stack.last() {
                                     unit
                                 }
                                 else {break;}
@@ -188,7 +326,7 @@ impl Expression {
                             if stk_pivot <= pivot {break;}
                             ans.push(stack.pop().unwrap());
                         }
-                        stack.push(u.clone());
+                        stack.push((u.clone(), *span));
                     }
                 },
             }
@@ -203,27 +341,189 @@ impl Expression {
         ans
     }
 
+    pub fn get_group_names(&self) -> Vec<Option<String>> {
+        let mut names: Vec<Option<String>> = vec![None];
+        for u in &self.units {
+            if let Unit::Operator(Operator::Capture(index, name)) = u {
+                if names.len() <= *index {
+                    names.resize(*index + 1, None);
+                }
+                names[*index] = name.clone();
+            }
+        }
+
+        names
+    }
+
+    // Whether the set read so far is the `[a-z-` that a subtracted set
+    // follows.
+    fn opens_subtraction(stack: &str) -> bool {
+        let chars: Vec<char> = stack.chars().collect();
+        chars.len() == 5 && chars[0] == '[' && chars[2] == '-' && chars[4] == '-'
+    }
+
+    // Reads the `?P<name>` or `?<name>` following an opening parenthese.
+    fn take_group_name(chars: &mut Peekable<CharIndices>) -> Option<String> {
+        let mut lookahead = chars.clone();
+        let mut next = || lookahead.next().map(|(_pos, ch)| ch);
+
+        if next() != Some('?') {
+            return None
+        }
+
+        match next() {
+            Some('P') => {
+                if next() != Some('<') {
+                    return None
+                }
+            },
+            Some('<') => {},
+            _ => return None,
+        }
+
+        let mut name = String::new();
+        loop {
+            match next() {
+                Some('>') => break,
+                Some(ch) => name.push(ch),
+                None => return None,
+            }
+        }
+
+        *chars = lookahead;
+
+        Some(name)
+    }
+
+    // Prints the pattern in the infix syntax it is parsed from, with only
+    // the parentheses the precedence of the operators needs.
     pub fn to_string(&self) -> String {
-        let mut res: String = String::new();
+        // precedence of what is on the stack: 1 alternation, 2 intersection,
+        // 3 concatenation, 4 complement, 5 repeat, 6 atom
+        let mut stack: Vec<(String, u32)> = Vec::new();
+        let wrap = |(res, pivot): (String, u32), min: u32| {
+            if pivot < min { format!("({})", res) } else { res }
+        };
 
         for u in self.units.iter() {
             match u {
                 Unit::Operator(op) => {
                     match op {
-                        Operator::LeftParenthese() => res.push('('),
-                        Operator::RightParenthese() => res.push(')'),
-                        Operator::Alternation() => res.push('|'),
-                        Operator::Concatenation() => res.push('.'),
-                        Operator::Repeat(rp) => res += &rp.to_string(),
+                        Operator::Alternation() => {
+                            let rhs = stack.pop().expect("Failed to get operand");
+                            let lhs = stack.pop().expect("Failed to get operand");
+                            stack.push((format!("{}|{}", lhs.0, rhs.0), 1));
+                        },
+                        Operator::Intersection() => {
+                            let rhs = stack.pop().expect("Failed to get operand");
+                            let lhs = stack.pop().expect("Failed to get operand");
+                            stack.push((format!("{}&{}", wrap(lhs, 2), wrap(rhs, 2)), 2));
+                        },
+                        Operator::Concatenation() => {
+                            let rhs = stack.pop().expect("Failed to get operand");
+                            let lhs = stack.pop().expect("Failed to get operand");
+                            stack.push((format!("{}{}", wrap(lhs, 3), wrap(rhs, 3)), 3));
+                        },
+                        Operator::Complement() => {
+                            let operand = stack.pop().expect("Failed to get operand");
+                            stack.push((format!("~{}", wrap(operand, 4)), 4));
+                        },
+                        Operator::Repeat(rp) => {
+                            let operand = stack.pop().expect("Failed to get operand");
+                            let rp = match rp {
+                                Repeat::From(1) => String::from("+"),
+                                rp => rp.to_string(),
+                            };
+                            stack.push((format!("{}{}", wrap(operand, 6), rp), 5));
+                        },
+                        Operator::Capture(_index, name) => {
+                            let operand = stack.pop().expect("Failed to get operand");
+                            let name = name.as_ref().map_or(String::new(), |name| format!("?P<{}>", name));
+                            stack.push((format!("({}{})", name, operand.0), 6));
+                        },
+                        Operator::LeftParenthese(_) |
+                        Operator::RightParenthese() => {},
                     }
                 },
                 Unit::Char(ch) => {
-                    res += &ch.to_string();
+                    stack.push((Expression::char_to_string(ch), 6));
                 }
             }
         }
 
-        res
+        stack.pop().map_or(String::new(), |top| top.0)
+    }
+
+    // A char class in a form the parser reads back: a bare char, `[abc]`,
+    // `[a-z]` or `[^a-z]`, or an alternation of those in parentheses.
+    fn char_to_string(ch: &Char) -> String {
+        let ranges = ch.to_ranges();
+
+        let mut pieces: Vec<String> = Vec::new();
+        let mut inner: &[(char, char)] = &ranges;
+
+        // a class holding both ends of the char space is written as the
+        // negation of the span between, plus what it holds inside that span
+        if ranges.len() > 1 && ranges[0].0 == '\0' && ranges[ranges.len() - 1].1 == std::char::MAX {
+            let from = next_char(ranges[0].1).expect("more ranges follow");
+            let to = prev_char(ranges[ranges.len() - 1].0).expect("more ranges precede");
+            pieces.push(format!("[^{}-{}]", from, to));
+            inner = &ranges[1..ranges.len() - 1];
+        }
+
+        // `]` ends any set it is in, so it is taken out of the ranges and
+        // written escaped
+        let mut closes = false;
+        let mut split: Vec<(char, char)> = Vec::new();
+        for &(a, b) in inner.iter() {
+            if a <= ']' && ']' <= b {
+                closes = true;
+                if a < ']' {
+                    split.push((a, '\\'));
+                }
+                if ']' < b {
+                    split.push(('^', b));
+                }
+            } else {
+                split.push((a, b));
+            }
+        }
+
+        let mut singles: Vec<char> = split.iter().filter(|(a, b)| a == b).map(|&(a, _b)| a).collect();
+        match singles.len() {
+            0 => {},
+            1 if !"()|*+?&~[{}\\".contains(singles[0]) => pieces.push(singles[0].to_string()),
+            _ => {
+                // '-' goes first so it is not read as a range, '^' never first
+                singles.sort_by_key(|&ch| (ch != '-', ch == '^'));
+                pieces.push(format!("[{}]", singles.iter().collect::<String>()));
+            },
+        }
+        if closes {
+            pieces.push(String::from("\\]"));
+        }
+        for &(a, b) in split.iter().filter(|(a, b)| a != b) {
+            pieces.push(format!("[{}-{}]", a, b));
+        }
+
+        if pieces.len() == 1 {
+            pieces.pop().expect("one piece")
+        } else {
+            format!("({})", pieces.join("|"))
+        }
+    }
+
+    // Parses like `from_str`, also returning the span of the pattern each
+    // unit was read from. Concatenations take an empty span.
+    pub fn from_str_with_spans(raw_str: &str) -> (Expression, Vec<Span>) {
+        Expression::try_from_str_with_spans(raw_str).expect("Failed to parse pattern")
+    }
+
+    pub fn try_from_str_with_spans(raw_str: &str) -> Result<(Expression, Vec<Span>), BuildError> {
+        let suffix_exp = Expression::spanned_inffix_to_suffix(Expression::str_to_spanned_inffix(raw_str)?);
+        let (units, spans) = suffix_exp.into_iter().unzip();
+
+        Ok((Expression { units }, spans))
     }
 
     pub fn from_str(raw_str: &String) -> Expression {
@@ -235,4 +535,8 @@ impl Expression {
             units: suffix_exp,
         }
     }
+
+    pub fn try_from_str(raw_str: &str) -> Result<Expression, BuildError> {
+        Ok(Expression::try_from_str_with_spans(raw_str)?.0)
+    }
 }
\ No newline at end of file
@@ -12,6 +12,8 @@ pub enum FaStateType {
     Head,
     Tail,
     Normal,
+    CaptureStart(usize),
+    CaptureEnd(usize),
 }
 
 #[derive(Clone)]
@@ -46,7 +48,9 @@ impl FaState {
                     self.index,
                 );
             },
-            FaStateType::Normal => {
+            FaStateType::Normal |
+            FaStateType::CaptureStart(_) |
+            FaStateType::CaptureEnd(_) => {
                 ans += &format!(
                     "  {} [shape=circle]\n",
                     self.index,
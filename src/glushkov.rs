@@ -0,0 +1,367 @@
+use crate::alphabet::*;
+use crate::dfa::*;
+use crate::fastate::*;
+use crate::error::BuildError;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use unit::*;
+
+// The position automaton of a pattern. State 0 is the start and state p,
+// from 1 to n, stands for the p-th char of the pattern and is only entered
+// by reading `chars[p - 1]`, so there are n + 1 states and no epsilon
+// trans. The follow lists keep the priority order of the pattern, and a 0
+// in them marks where accepting stands in that order, which makes
+// leftmost-first searches agree with the thompson nfa.
+pub struct Glushkov {
+    pub chars: Vec<Char>,
+    pub follow: Vec<Vec<usize>>,
+    pub accepting: Vec<bool>,
+}
+
+// Leaving the fragment, in the first and follow lists.
+const EXIT: usize = usize::MAX;
+
+// The positions of a part of the pattern, numbered from 0.
+#[derive(Clone)]
+struct Fragment {
+    chars: Vec<Char>,
+    first: Vec<usize>,
+    follow: Vec<Vec<usize>>,
+}
+
+fn push_new(list: &mut Vec<usize>, items: &[usize]) {
+    for &item in items.iter() {
+        if !list.contains(&item) {
+            list.push(item);
+        }
+    }
+}
+
+// `list` with `exit` in place of the exit.
+fn splice(list: &[usize], exit: &[usize]) -> Vec<usize> {
+    let mut res: Vec<usize> = Vec::new();
+    for &p in list.iter() {
+        if p == EXIT {
+            push_new(&mut res, exit);
+        } else {
+            push_new(&mut res, &[p]);
+        }
+    }
+
+    res
+}
+
+impl Fragment {
+    fn empty() -> Fragment {
+        Fragment {
+            chars: Vec::new(),
+            first: vec![EXIT],
+            follow: Vec::new(),
+        }
+    }
+
+    fn from_char(ch: &Char) -> Fragment {
+        Fragment {
+            chars: vec![ch.clone()],
+            first: vec![0],
+            follow: vec![vec![EXIT]],
+        }
+    }
+
+    // Positions plus the entries of the first and follow lists, which is
+    // what the size limit counts.
+    fn size(&self) -> usize {
+        self.chars.len() + self.first.len() + self.follow.iter().map(|follow| follow.len()).sum::<usize>()
+    }
+
+    // How many lists hold the exit, each of which gets a list spliced in.
+    fn exits(&self) -> usize {
+        self.follow.iter().chain(std::iter::once(&self.first)).filter(|list| list.contains(&EXIT)).count()
+    }
+
+    // Fails unless `size` fits in the limit.
+    fn check(size: usize, limit: usize) -> Result<(), BuildError> {
+        if size > limit {
+            return Err(BuildError::NfaTooBig { limit })
+        }
+
+        Ok(())
+    }
+
+    fn add_offset(&mut self, offset: usize) {
+        for p in self.first.iter_mut().chain(self.follow.iter_mut().flatten()) {
+            if *p != EXIT {
+                *p += offset;
+            }
+        }
+    }
+
+    // The size is checked before the lists are spliced, as they can grow
+    // with the product of the sizes.
+    fn concatenation(mut lhs: Fragment, mut rhs: Fragment, limit: usize) -> Result<Fragment, BuildError> {
+        let size = lhs.exits().saturating_mul(rhs.first.len()).saturating_add(lhs.size()).saturating_add(rhs.size());
+        Fragment::check(size, limit)?;
+
+        rhs.add_offset(lhs.chars.len());
+
+        for follow in lhs.follow.iter_mut() {
+            *follow = splice(follow, &rhs.first);
+        }
+        lhs.first = splice(&lhs.first, &rhs.first);
+        lhs.chars.append(&mut rhs.chars);
+        lhs.follow.append(&mut rhs.follow);
+
+        Ok(lhs)
+    }
+
+    fn alternation(mut lhs: Fragment, mut rhs: Fragment) -> Fragment {
+        rhs.add_offset(lhs.chars.len());
+
+        push_new(&mut lhs.first, &rhs.first);
+        lhs.chars.append(&mut rhs.chars);
+        lhs.follow.append(&mut rhs.follow);
+
+        lhs
+    }
+
+    // Going round again comes before leaving, as the repeat is greedy.
+    fn plus(mut self, limit: usize) -> Result<Fragment, BuildError> {
+        Fragment::check(self.exits().saturating_mul(self.first.len() + 1).saturating_add(self.size()), limit)?;
+
+        let mut again: Vec<usize> = self.first.iter().cloned().filter(|&p| p != EXIT).collect();
+        again.push(EXIT);
+
+        for follow in self.follow.iter_mut() {
+            *follow = splice(follow, &again);
+        }
+
+        Ok(self)
+    }
+
+    fn maybe(mut self) -> Fragment {
+        push_new(&mut self.first, &[EXIT]);
+        self
+    }
+
+    fn times(&self, times: usize, limit: usize) -> Result<Fragment, BuildError> {
+        (0..times).try_fold(Fragment::empty(), |res, _i| Fragment::concatenation(res, self.clone(), limit))
+    }
+
+    fn repeat(self, repeat: &Repeat, limit: usize) -> Result<Fragment, BuildError> {
+        Ok(match repeat {
+            Repeat::Exact(times) => self.times(*times, limit)?,
+            Repeat::FromZero() | Repeat::From(0) => self.plus(limit)?.maybe(),
+            Repeat::From(from) => Fragment::concatenation(self.times(from - 1, limit)?, self.plus(limit)?, limit)?,
+            Repeat::FromTo(from, to) => {
+                let rest = (*from..*to).try_fold(Fragment::empty(), |rest, _i| {
+                    Ok(Fragment::concatenation(self.clone(), rest, limit)?.maybe())
+                })?;
+                Fragment::concatenation(self.times(*from, limit)?, rest, limit)?
+            },
+            Repeat::Maybe() => self.maybe(),
+        })
+    }
+}
+
+impl Glushkov {
+    // `None` for patterns with an intersection or a complement, which have
+    // no position automaton.
+    pub fn from_expression(expression: &[Unit]) -> Option<Glushkov> {
+        Glushkov::from_expression_limited(expression, false, usize::MAX).expect("Failed to get glushkov")
+    }
+
+    // The position automaton of the pattern read backwards.
+    pub fn from_expression_reversed(expression: &[Unit]) -> Option<Glushkov> {
+        Glushkov::from_expression_limited(expression, true, usize::MAX).expect("Failed to get glushkov")
+    }
+
+    // Fails once the positions and the entries of their first and follow
+    // lists, over all the fragments built so far, pass `limit`. The follow
+    // lists can grow with the square of the positions, so this is checked
+    // before each list is spliced, not only after each unit.
+    pub(crate) fn from_expression_limited(expression: &[Unit], reversed: bool, limit: usize) -> Result<Option<Glushkov>, BuildError> {
+        let mut fragments: Vec<Fragment> = Vec::new();
+        for unit in expression {
+            match unit {
+                Unit::Char(ch) => fragments.push(Fragment::from_char(ch)),
+                Unit::Operator(Operator::Concatenation()) => {
+                    let rhs = fragments.pop().expect("Failed to get fragment");
+                    let lhs = fragments.pop().expect("Failed to get fragment");
+                    fragments.push(if reversed {
+                        Fragment::concatenation(rhs, lhs, limit)?
+                    } else {
+                        Fragment::concatenation(lhs, rhs, limit)?
+                    });
+                },
+                Unit::Operator(Operator::Alternation()) => {
+                    let rhs = fragments.pop().expect("Failed to get fragment");
+                    let lhs = fragments.pop().expect("Failed to get fragment");
+                    fragments.push(Fragment::alternation(lhs, rhs));
+                },
+                Unit::Operator(Operator::Repeat(repeat)) => {
+                    let fragment = fragments.pop().expect("Failed to get fragment");
+                    fragments.push(fragment.repeat(repeat, limit)?);
+                },
+                Unit::Operator(Operator::Capture(_index, _name)) => {},
+                Unit::Operator(Operator::Intersection()) |
+                Unit::Operator(Operator::Complement()) => return Ok(None),
+                _ => panic!("unexpected unit"),
+            }
+
+            Fragment::check(fragments.iter().map(|fragment| fragment.size()).sum(), limit)?;
+        }
+
+        let fragment = fragments.pop().expect("Generate Glushkov Error.");
+        let shift = |positions: &[usize]| -> Vec<usize> {
+            positions.iter().map(|&p| if p == EXIT { 0 } else { p + 1 }).collect()
+        };
+
+        let mut follow: Vec<Vec<usize>> = vec![shift(&fragment.first)];
+        follow.extend(fragment.follow.iter().map(|positions| shift(positions)));
+        let accepting = follow.iter().map(|positions| positions.contains(&0)).collect();
+
+        Ok(Some(Glushkov {
+            chars: fragment.chars,
+            follow,
+            accepting,
+        }))
+    }
+
+    pub fn get_chars(&self) -> HashSet<Char> {
+        self.chars.iter().cloned().collect()
+    }
+
+    // The automaton as nfa states: a tran into p reads the char of p, and
+    // the accepting states are marked as tails.
+    pub fn states(&self) -> Vec<FaState> {
+        self.follow.iter().enumerate().map(|(p, follow)| FaState {
+            index: p,
+            trans: follow.iter().filter(|&&q| q != 0).map(|&q| Tran::Char(self.chars[q - 1].clone(), q)).collect(),
+            kind: if self.accepting[p] {
+                FaStateType::Tail
+            } else if p == 0 {
+                FaStateType::Head
+            } else {
+                FaStateType::Normal
+            },
+        }).collect()
+    }
+}
+
+// A thread waits to enter the position it stands for, and 0 is a thread
+// that accepts, like the tail of the thompson nfa.
+impl Threads for Glushkov {
+    fn state_count(&self) -> usize {
+        self.follow.len()
+    }
+
+    fn is_final(&self, i: usize) -> bool {
+        i == 0
+    }
+
+    fn start(&self, seen: &mut [bool], threads: &mut Vec<usize>) {
+        for &q in self.follow[0].iter() {
+            if !seen[q] {
+                seen[q] = true;
+                threads.push(q);
+            }
+        }
+    }
+
+    fn step(&self, i: usize, ch: char, seen: &mut [bool], threads: &mut Vec<usize>) {
+        if i == 0 || !self.chars[i - 1].is_match(ch) {
+            return
+        }
+
+        for &q in self.follow[i].iter() {
+            if !seen[q] {
+                seen[q] = true;
+                threads.push(q);
+            }
+        }
+    }
+}
+
+impl Dfa {
+    pub fn from_glushkov(glushkov: &Glushkov) -> Dfa {
+        Dfa::from_glushkov_limited(glushkov, usize::MAX).expect("Failed to build dfa")
+    }
+
+    // The subset construction, without any closure to take. Fails instead of
+    // adding a state past the first `limit` ones.
+    pub(crate) fn from_glushkov_limited(glushkov: &Glushkov, limit: usize) -> Result<Dfa, BuildError> {
+        let classes = classes(&glushkov.get_chars());
+
+        let mut dfa = Dfa {
+            head: 0,
+            tail: vec![],
+            states: Vec::new(),
+        };
+        let mut raw_states: Vec<Vec<usize>> = Vec::new();
+        let mut map: HashMap<Vec<usize>, usize> = HashMap::new();
+
+        let add_state = |dfa: &mut Dfa, raw_states: &mut Vec<Vec<usize>>, map: &mut HashMap<Vec<usize>, usize>, set: Vec<usize>| {
+            let id = dfa.states.len();
+            let kind = if set.iter().any(|&p| glushkov.accepting[p]) {
+                dfa.tail.push(id);
+                FaStateType::Tail
+            } else if id == 0 {
+                FaStateType::Head
+            } else {
+                FaStateType::Normal
+            };
+
+            dfa.states.push(FaState {
+                index: id,
+                trans: HashSet::new(),
+                kind,
+            });
+            map.insert(set.clone(), id);
+            raw_states.push(set);
+
+            id
+        };
+        add_state(&mut dfa, &mut raw_states, &mut map, vec![0]);
+
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        queue.push_back(0);
+
+        while let Some(t) = queue.pop_front() {
+            for class in classes.iter() {
+                let ch = representative(class);
+                let mut u: Vec<usize> = raw_states[t].iter()
+                    .flat_map(|&p| glushkov.follow[p].iter().cloned())
+                    .filter(|&q| q != 0 && glushkov.chars[q - 1].is_match(ch))
+                    .collect();
+                u.sort();
+                u.dedup();
+                if u.is_empty() {
+                    continue;
+                }
+
+                let u_id = match map.get(&u) {
+                    Some(&u_id) => u_id,
+                    None if dfa.states.len() >= limit => return Err(BuildError::DfaTooBig { limit }),
+                    None => {
+                        let u_id = add_state(&mut dfa, &mut raw_states, &mut map, u);
+                        queue.push_back(u_id);
+                        u_id
+                    },
+                };
+
+                dfa.states[t].add_char_tran(class.clone(), u_id);
+            }
+        }
+
+        Ok(dfa)
+    }
+
+    pub fn from_glushkov_unanchored(glushkov: &Glushkov, kind: MatchKind, allow_empty: bool) -> Dfa {
+        Dfa::from_threads(glushkov, glushkov.get_chars(), kind, false, allow_empty, usize::MAX).expect("Failed to build dfa")
+    }
+
+    pub fn from_glushkov_anchored(glushkov: &Glushkov, kind: MatchKind, allow_empty: bool) -> Dfa {
+        Dfa::from_threads(glushkov, glushkov.get_chars(), kind, true, allow_empty, usize::MAX).expect("Failed to build dfa")
+    }
+}
@@ -1,5 +1,26 @@
 pub mod nfa;
+pub mod glushkov;
+pub mod simplify;
+pub mod bitparallel;
 pub mod dfa;
 pub mod regex;
 pub mod fastate;
-pub mod expression;
\ No newline at end of file
+pub mod expression;
+pub mod literal;
+pub mod alphabet;
+pub mod pikevm;
+pub mod approx;
+pub mod matches;
+pub mod stream;
+pub mod table;
+pub mod serialize;
+pub mod ops;
+pub mod analysis;
+pub mod enumerate;
+pub mod sample;
+pub mod ast;
+pub mod derivative;
+pub mod lint;
+pub mod ambiguity;
+pub mod budget;
+pub mod error;
\ No newline at end of file
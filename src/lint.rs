@@ -0,0 +1,341 @@
+use crate::ast::Ast;
+use crate::dfa::Dfa;
+use crate::error::BuildError;
+use crate::expression::*;
+use unit::*;
+
+// Bounded repeats are expanded into copies of their operand, so a pattern
+// needing more copies than this is reported.
+pub const HUGE_REPEAT: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LintKind {
+    EmptyMatch,
+    NestedQuantifier,
+    RedundantBranch,
+    UnreachableBranch,
+    OverlappingRanges,
+    HugeRepeat,
+    Syntax,
+}
+
+impl LintKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintKind::EmptyMatch => "empty-match",
+            LintKind::NestedQuantifier => "nested-quantifier",
+            LintKind::RedundantBranch => "redundant-branch",
+            LintKind::UnreachableBranch => "unreachable-branch",
+            LintKind::OverlappingRanges => "overlapping-ranges",
+            LintKind::HugeRepeat => "huge-repeat",
+            LintKind::Syntax => "syntax",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Warning {
+    pub kind: LintKind,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Warning {
+    // The message followed by the pattern with the span underlined.
+    pub fn render(&self, pattern: &str) -> String {
+        let offset = pattern[..self.span.0].chars().count();
+        let width = pattern[self.span.0..self.span.1].chars().count().max(1);
+
+        format!("warning[{}]: {}\n  {}\n  {}{}",
+            self.kind.name(), self.message, pattern, " ".repeat(offset), "^".repeat(width))
+    }
+}
+
+// A node of the parsed pattern, stored at the index of its unit in the
+// postfix expression. The units from `first` to that index are the
+// subexpression of the node.
+struct Node {
+    children: Vec<usize>,
+    first: usize,
+    span: Span,
+}
+
+struct Tree<'a> {
+    pattern: &'a str,
+    units: Vec<Unit>,
+    nodes: Vec<Node>,
+    parents: Vec<Option<usize>>,
+    root: usize,
+}
+
+impl<'a> Tree<'a> {
+    fn from_str(pattern: &'a str) -> Result<Tree<'a>, BuildError> {
+        let (expression, spans) = Expression::try_from_str_with_spans(pattern)?;
+
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut parents: Vec<Option<usize>> = Vec::new();
+        let mut stack: Vec<usize> = Vec::new();
+        for (i, u) in expression.units.iter().enumerate() {
+            let arity = match u {
+                Unit::Char(_ch) => 0,
+                Unit::Operator(Operator::Alternation()) |
+                Unit::Operator(Operator::Intersection()) |
+                Unit::Operator(Operator::Concatenation()) => 2,
+                _ => 1,
+            };
+
+            let children = stack.split_off(stack.len() - arity);
+            let first = children.first().map_or(i, |&child| nodes[child].first);
+            let mut span = spans[i];
+            for &child in children.iter() {
+                parents[child] = Some(i);
+                span = (span.0.min(nodes[child].span.0), span.1.max(nodes[child].span.1));
+            }
+
+            nodes.push(Node { children, first, span });
+            parents.push(None);
+            stack.push(i);
+        }
+
+        Ok(Tree {
+            pattern,
+            root: stack.pop().expect("Failed to get root"),
+            units: expression.units,
+            nodes,
+            parents,
+        })
+    }
+
+    fn text(&self, node: usize) -> &'a str {
+        let span = self.nodes[node].span;
+        &self.pattern[span.0..span.1]
+    }
+
+    fn ast(&self, node: usize) -> Ast {
+        Ast::from_expression(&self.units[self.nodes[node].first..=node])
+    }
+
+    // The node under any captures around it.
+    fn strip(&self, mut node: usize) -> usize {
+        while let Unit::Operator(Operator::Capture(_index, _name)) = &self.units[node] {
+            node = self.nodes[node].children[0];
+        }
+
+        node
+    }
+
+    fn is_alternation(&self, node: usize) -> bool {
+        self.units[node] == Unit::Operator(Operator::Alternation())
+    }
+
+    fn is_unbounded_repeat(&self, node: usize) -> bool {
+        matches!(self.units[node], Unit::Operator(Operator::Repeat(Repeat::FromZero())) |
+            Unit::Operator(Operator::Repeat(Repeat::From(_))))
+    }
+
+    // The branches of the alternation chain at `node`, left to right.
+    fn branches(&self, node: usize) -> Vec<usize> {
+        if !self.is_alternation(node) {
+            return vec![node];
+        }
+
+        self.nodes[node].children.iter().flat_map(|&child| self.branches(child)).collect()
+    }
+}
+
+// Whether every string `lhs` matches is also matched by `rhs`.
+fn is_subset(lhs: &Ast, rhs: &Ast) -> bool {
+    Dfa::from_ast(&Ast::and(lhs.clone(), Ast::complement(rhs.clone()))).is_empty()
+}
+
+fn overlap(lhs: &Char, rhs: &Char) -> Option<(char, char)> {
+    for &(a, b) in lhs.to_ranges().iter() {
+        for &(c, d) in rhs.to_ranges().iter() {
+            if a.max(c) <= b.min(d) {
+                return Some((a.max(c), b.min(d)));
+            }
+        }
+    }
+
+    None
+}
+
+fn range_to_string((a, b): (char, char)) -> String {
+    if a == b { format!("`{}`", a) } else { format!("`{}`-`{}`", a, b) }
+}
+
+fn repeat_count(rp: &Repeat) -> usize {
+    match rp {
+        Repeat::Exact(times) => *times,
+        Repeat::FromTo(_from, to) => *to,
+        Repeat::From(from) => (*from).max(1),
+        Repeat::FromZero() | Repeat::Maybe() => 1,
+    }
+}
+
+// Inspects the pattern for constructs that are likely mistakes or costly to
+// build, returning the warnings ordered by where they start.
+// A pattern that does not parse gets a single syntax warning under the char
+// where parsing failed.
+pub fn lint(pattern: &str) -> Vec<Warning> {
+    let tree = match Tree::from_str(pattern) {
+        Ok(tree) => tree,
+        Err(BuildError::Syntax { at, message }) => {
+            let width = pattern[at..].chars().next().map_or(0, |ch| ch.len_utf8());
+            return vec![Warning {
+                kind: LintKind::Syntax,
+                span: (at, at + width),
+                message: format!("syntax error: {}", message),
+            }]
+        },
+        Err(error) => panic!("unexpected error: {}", error),
+    };
+    let mut warnings: Vec<Warning> = Vec::new();
+
+    // the checks on languages build the repeats out, which is left to the
+    // huge repeat warning alone
+    lint_repeat_size(&tree, tree.root, &mut warnings);
+    let huge = !warnings.is_empty();
+
+    if !huge && tree.ast(tree.root).is_nullable() {
+        warnings.push(Warning {
+            kind: LintKind::EmptyMatch,
+            span: (0, pattern.len()),
+            message: String::from("the pattern matches the empty string"),
+        });
+    }
+
+    for node in 0..tree.nodes.len() {
+        match &tree.units[node] {
+            Unit::Char(Char::Set(chars)) => lint_set(&tree, node, chars, &mut warnings),
+            Unit::Operator(Operator::Repeat(rp)) => lint_nested(&tree, node, rp, &mut warnings),
+            Unit::Operator(Operator::Alternation()) if !huge && !tree.parents[node].is_some_and(|parent| tree.is_alternation(parent)) => {
+                lint_branches(&tree, node, &mut warnings);
+            },
+            _ => {},
+        }
+    }
+
+    warnings.sort_by_key(|warning| warning.span.0);
+    warnings
+}
+
+fn lint_set(tree: &Tree, node: usize, chars: &[char], warnings: &mut Vec<Warning>) {
+    for (i, ch) in chars.iter().enumerate() {
+        if chars[..i].contains(ch) && !chars[i + 1..].contains(ch) {
+            warnings.push(Warning {
+                kind: LintKind::OverlappingRanges,
+                span: tree.nodes[node].span,
+                message: format!("`{}` is listed more than once in the class", ch),
+            });
+        }
+    }
+}
+
+fn lint_nested(tree: &Tree, node: usize, rp: &Repeat, warnings: &mut Vec<Warning>) {
+    let operand = tree.strip(tree.nodes[node].children[0]);
+
+    let message = if tree.is_unbounded_repeat(node) && tree.ast(operand).is_nullable() {
+        "the repeated operand matches the empty string"
+    } else if repeat_count(rp) > 1 && tree.is_unbounded_repeat(operand) {
+        "the repeated operand is itself an unbounded repeat"
+    } else {
+        return;
+    };
+
+    warnings.push(Warning {
+        kind: LintKind::NestedQuantifier,
+        span: tree.nodes[node].span,
+        message: format!("nested quantifier, {}: `{}`", message, tree.text(operand)),
+    });
+}
+
+fn lint_branches(tree: &Tree, node: usize, warnings: &mut Vec<Warning>) {
+    let branches = tree.branches(node);
+    let asts: Vec<Ast> = branches.iter().map(|&branch| tree.ast(branch)).collect();
+
+    // a branch is redundant when another one matches everything it does,
+    // of two branches matching the same strings the later is reported
+    let mut redundant = vec![false; branches.len()];
+    for i in 0..branches.len() {
+        let other = (0..branches.len()).find(|&j| {
+            j != i && !redundant[j] && is_subset(&asts[i], &asts[j]) && (j < i || !is_subset(&asts[j], &asts[i]))
+        });
+        if let Some(j) = other {
+            redundant[i] = true;
+            warnings.push(Warning {
+                kind: LintKind::RedundantBranch,
+                span: tree.nodes[branches[i]].span,
+                message: format!("redundant branch, everything it matches is matched by `{}`", tree.text(branches[j])),
+            });
+        }
+    }
+
+    for i in 0..branches.len() {
+        for j in i + 1..branches.len() {
+            if redundant[i] || redundant[j] {
+                continue;
+            }
+
+            if let (Unit::Char(lhs), Unit::Char(rhs)) = (&tree.units[tree.strip(branches[i])], &tree.units[tree.strip(branches[j])]) {
+                if let Some(range) = overlap(lhs, rhs) {
+                    warnings.push(Warning {
+                        kind: LintKind::OverlappingRanges,
+                        span: tree.nodes[branches[j]].span,
+                        message: format!("the class overlaps `{}` on {}", tree.text(branches[i]), range_to_string(range)),
+                    });
+                }
+            }
+        }
+    }
+
+    // under leftmost-first an earlier branch of the whole pattern wins as
+    // soon as it matches, so a branch all of whose strings start with a
+    // string of an earlier branch never matches
+    if tree.strip(tree.root) != node {
+        return;
+    }
+
+    let mut earlier = asts[0].clone();
+    for i in 1..branches.len() {
+        let prefixes = Ast::concat(earlier.clone(), Ast::complement(Ast::Nothing));
+        if !redundant[i] && is_subset(&asts[i], &prefixes) {
+            warnings.push(Warning {
+                kind: LintKind::UnreachableBranch,
+                span: tree.nodes[branches[i]].span,
+                message: String::from("unreachable branch under leftmost-first, an earlier branch always matches first"),
+            });
+        }
+        earlier = Ast::alternate(earlier, asts[i].clone());
+    }
+}
+
+// How many copies of the innermost operand the repeats in the subtree of
+// `node` make at most.
+fn copies(tree: &Tree, node: usize) -> usize {
+    let inner = tree.nodes[node].children.iter().map(|&child| copies(tree, child)).max().unwrap_or(1);
+    match &tree.units[node] {
+        Unit::Operator(Operator::Repeat(rp)) => inner.saturating_mul(repeat_count(rp)),
+        _ => inner,
+    }
+}
+
+// Reports the outermost repeat whose expansion goes over the limit, and
+// nothing inside it.
+fn lint_repeat_size(tree: &Tree, node: usize, warnings: &mut Vec<Warning>) {
+    if let Unit::Operator(Operator::Repeat(_rp)) = &tree.units[node] {
+        let copies = copies(tree, node);
+        if copies > HUGE_REPEAT {
+            warnings.push(Warning {
+                kind: LintKind::HugeRepeat,
+                span: tree.nodes[node].span,
+                message: format!("huge repeat, it is expanded into {} copies of its operand", copies),
+            });
+            return;
+        }
+    }
+
+    for &child in tree.nodes[node].children.iter() {
+        lint_repeat_size(tree, child, warnings);
+    }
+}
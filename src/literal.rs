@@ -0,0 +1,255 @@
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+use unit::*;
+
+const MAX_LITERALS: usize = 32;
+const MAX_CLASS_SIZE: u32 = 8;
+
+#[derive(Clone)]
+struct Info {
+    exact: Option<Vec<String>>,
+    prefixes: Option<Vec<String>>,
+    required: Option<String>,
+}
+
+fn cross(lhs: &[String], rhs: &[String]) -> Option<Vec<String>> {
+    if lhs.len() * rhs.len() > MAX_LITERALS {
+        return None
+    }
+
+    let mut res: Vec<String> = Vec::new();
+    for a in lhs.iter() {
+        for b in rhs.iter() {
+            let s = format!("{}{}", a, b);
+            if !res.contains(&s) {
+                res.push(s);
+            }
+        }
+    }
+
+    Some(res)
+}
+
+fn union(lhs: &[String], rhs: &[String]) -> Option<Vec<String>> {
+    let mut res = lhs.to_vec();
+    for s in rhs.iter() {
+        if !res.contains(s) {
+            res.push(s.clone());
+        }
+    }
+
+    if res.len() > MAX_LITERALS {
+        None
+    } else {
+        Some(res)
+    }
+}
+
+fn longer(a: Option<String>, b: Option<String>) -> Option<String> {
+    match (a, b) {
+        (Some(a), Some(b)) => if b.len() > a.len() { Some(b) } else { Some(a) },
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+impl Info {
+    fn unknown() -> Info {
+        Info { exact: None, prefixes: None, required: None }
+    }
+
+    fn from_strings(strings: Vec<String>) -> Info {
+        let required = if strings.len() == 1 { Some(strings[0].clone()) } else { None };
+
+        Info {
+            exact: Some(strings.clone()),
+            prefixes: Some(strings),
+            required,
+        }
+    }
+
+    fn from_char(ch: &Char) -> Info {
+        let chars: Vec<char> = match ch {
+            Char::Single(a) => vec![*a],
+            Char::Set(set) if set.len() <= MAX_LITERALS => set.clone(),
+            Char::Range(a, b) if (*b as u32).saturating_sub(*a as u32) < MAX_CLASS_SIZE => {
+                (*a..=*b).collect()
+            },
+            _ => return Info::unknown(),
+        };
+
+        Info::from_strings(chars.iter().map(|c| c.to_string()).collect())
+    }
+
+    fn concatenation(lhs: Info, rhs: Info) -> Info {
+        let exact = match (&lhs.exact, &rhs.exact) {
+            (Some(a), Some(b)) => cross(a, b),
+            _ => None,
+        };
+
+        let prefixes = match (&lhs.exact, &rhs.prefixes) {
+            (Some(a), Some(b)) => cross(a, b).or_else(|| Some(a.clone())),
+            (Some(a), None) => Some(a.clone()),
+            (None, _) => lhs.prefixes.clone(),
+        };
+
+        let required = match (&exact, &prefixes) {
+            (Some(exact), _) if exact.len() == 1 => Some(exact[0].clone()),
+            (_, Some(prefixes)) if prefixes.len() == 1 => {
+                longer(Some(prefixes[0].clone()), longer(lhs.required, rhs.required))
+            },
+            _ => longer(lhs.required, rhs.required),
+        };
+
+        Info { exact, prefixes, required }
+    }
+
+    fn alternation(lhs: Info, rhs: Info) -> Info {
+        let exact = match (&lhs.exact, &rhs.exact) {
+            (Some(a), Some(b)) => union(a, b),
+            _ => None,
+        };
+
+        let prefixes = match (&lhs.prefixes, &rhs.prefixes) {
+            (Some(a), Some(b)) => union(a, b),
+            _ => None,
+        };
+
+        let required = if lhs.required == rhs.required { lhs.required } else { None };
+
+        Info { exact, prefixes, required }
+    }
+
+    fn repeat(info: Info, min: usize, max: Option<usize>) -> Info {
+        if min == 0 {
+            return match (max, info.exact) {
+                (Some(1), Some(mut exact)) => {
+                    exact.push(String::new());
+                    Info { exact: Some(exact), prefixes: None, required: None }
+                },
+                _ => Info::unknown(),
+            }
+        }
+
+        let mut res = info.clone();
+        for _i in 1..min {
+            res = Info::concatenation(res, info.clone());
+            if res.exact.is_none() {
+                break;
+            }
+        }
+
+        if max != Some(min) {
+            res.exact = None;
+        }
+
+        res
+    }
+}
+
+pub struct Literals {
+    pub prefixes: Vec<String>,
+    pub required: Option<String>,
+}
+
+impl Literals {
+    pub fn from_expression(expression: &[Unit]) -> Literals {
+        let mut stack: Vec<Info> = Vec::new();
+
+        for unit in expression {
+            let info = match unit {
+                Unit::Char(ch) => Info::from_char(ch),
+                Unit::Operator(Operator::Concatenation()) => {
+                    let rhs = stack.pop().expect("Failed to get literals");
+                    let lhs = stack.pop().expect("Failed to get literals");
+                    Info::concatenation(lhs, rhs)
+                },
+                Unit::Operator(Operator::Alternation()) => {
+                    let rhs = stack.pop().expect("Failed to get literals");
+                    let lhs = stack.pop().expect("Failed to get literals");
+                    Info::alternation(lhs, rhs)
+                },
+                // a match of both sides is a match of the left one
+                Unit::Operator(Operator::Intersection()) => {
+                    stack.pop().expect("Failed to get literals");
+                    let lhs = stack.pop().expect("Failed to get literals");
+                    Info { exact: None, ..lhs }
+                },
+                Unit::Operator(Operator::Complement()) => {
+                    stack.pop().expect("Failed to get literals");
+                    Info::unknown()
+                },
+                Unit::Operator(Operator::Repeat(repeat)) => {
+                    let info = stack.pop().expect("Failed to get literals");
+                    match repeat {
+                        Repeat::Exact(times) => Info::repeat(info, *times, Some(*times)),
+                        Repeat::FromZero() => Info::repeat(info, 0, None),
+                        Repeat::From(from) => Info::repeat(info, *from, None),
+                        Repeat::FromTo(from, to) => Info::repeat(info, *from, Some(*to)),
+                        Repeat::Maybe() => Info::repeat(info, 0, Some(1)),
+                    }
+                },
+                Unit::Operator(Operator::Capture(_index, _name)) => {
+                    stack.pop().expect("Failed to get literals")
+                },
+                _ => Info::unknown(),
+            };
+
+            stack.push(info);
+        }
+
+        let info = stack.pop().unwrap_or_else(Info::unknown);
+
+        let prefixes = match info.prefixes {
+            Some(prefixes) if prefixes.iter().all(|s| !s.is_empty()) => prefixes,
+            _ => Vec::new(),
+        };
+
+        Literals {
+            prefixes,
+            required: info.required.filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+pub enum Prefilter {
+    Byte(u8),
+    Literal(String),
+    Multiple(Box<AhoCorasick>),
+}
+
+impl Prefilter {
+    pub fn new(prefixes: &[String]) -> Option<Prefilter> {
+        match prefixes.len() {
+            0 => None,
+            1 if prefixes[0].len() == 1 => Some(Prefilter::Byte(prefixes[0].as_bytes()[0])),
+            1 => Some(Prefilter::Literal(prefixes[0].clone())),
+            _ => Some(Prefilter::Multiple(Box::new(
+                AhoCorasickBuilder::new()
+                    .match_kind(MatchKind::LeftmostFirst)
+                    .build(prefixes)
+            ))),
+        }
+    }
+
+    // Returns the first position at or after `at` where a match could start.
+    pub fn find(&self, content: &str, at: usize) -> Option<usize> {
+        let haystack = &content.as_bytes()[at..];
+
+        match self {
+            Prefilter::Byte(byte) => memchr::memchr(*byte, haystack).map(|i| at + i),
+            Prefilter::Literal(literal) => {
+                let literal = literal.as_bytes();
+                let mut i = 0;
+                while let Some(j) = memchr::memchr(literal[0], &haystack[i..]) {
+                    if haystack[i + j..].starts_with(literal) {
+                        return Some(at + i + j)
+                    }
+                    i += j + 1;
+                }
+
+                None
+            },
+            Prefilter::Multiple(automaton) => automaton.find(haystack).map(|m| at + m.start()),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use lexer::ambiguity::*;
+use lexer::lint::*;
 use lexer::regex::*;
 
 fn write_to_file(path: &str, content: &str) -> std::io::Result<()> {
@@ -17,6 +19,30 @@ fn main() {
 
     println!("{:?}", args);
 
+    // `lint <pattern>` prints the warnings for the pattern instead
+    if args.len() > 2 && args[1] == "lint" {
+        let warnings = lint(&args[2]);
+        for warning in warnings.iter() {
+            println!("{}", warning.render(&args[2]));
+        }
+        println!("{} warning(s)", warnings.len());
+        return;
+    }
+
+    // `rules <pattern>...` prints the conflicts between lexer rules given
+    // from the highest priority down
+    if args.len() > 2 && args[1] == "rules" {
+        let names: Vec<&str> = args[2..].iter().map(|arg| arg.as_str()).collect();
+        let rules: Vec<Regex> = args[2..].iter().map(|arg| Regex::from(arg)).collect();
+        let ambiguities = Ambiguities::from_rules(&rules);
+        if ambiguities.is_empty() {
+            println!("no conflicts");
+        } else {
+            println!("{}", ambiguities.render(&names));
+        }
+        return;
+    }
+
     let regex = Regex::from(&args[1]);
 
     let strings = regex.to_strings();
@@ -0,0 +1,409 @@
+use crate::budget::*;
+use crate::regex::Regex;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'t> {
+    text: &'t str,
+    start: usize,
+    end: usize,
+}
+
+impl<'t> Match<'t> {
+    pub fn new(text: &'t str, start: usize, end: usize) -> Match<'t> {
+        Match { text, start, end }
+    }
+
+    pub fn text(&self) -> &'t str {
+        self.text
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn as_str(&self) -> &'t str {
+        &self.text[self.start..self.end]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+pub struct Captures<'t> {
+    text: &'t str,
+    slots: Vec<Option<usize>>,
+    names: HashMap<String, usize>,
+}
+
+impl<'t> Captures<'t> {
+    pub fn new(text: &'t str, slots: Vec<Option<usize>>, names: &[Option<String>]) -> Captures<'t> {
+        let names = names.iter()
+            .enumerate()
+            .filter_map(|(i, name)| name.as_ref().map(|name| (name.clone(), i)))
+            .collect();
+
+        Captures { text, slots, names }
+    }
+
+    pub fn get(&self, index: usize) -> Option<Match<'t>> {
+        match (self.slots.get(index * 2), self.slots.get(index * 2 + 1)) {
+            (Some(Some(start)), Some(Some(end))) => Some(Match::new(self.text, *start, *end)),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self, name: &str) -> Option<Match<'t>> {
+        self.names.get(name).and_then(|&index| self.get(index))
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    // Appends `replacement` to `dst`, with `$1`, `${1}`, `$name` and
+    // `${name}` replaced by the group text and `$$` by a single `$`.
+    pub fn expand(&self, replacement: &str, dst: &mut String) {
+        let mut rest = replacement;
+
+        while let Some(i) = rest.find('$') {
+            dst.push_str(&rest[..i]);
+            rest = &rest[i + 1..];
+
+            if rest.starts_with('$') {
+                dst.push('$');
+                rest = &rest[1..];
+                continue;
+            }
+
+            let (name, len) = if rest.starts_with('{') {
+                match rest.find('}') {
+                    Some(j) => (&rest[1..j], j + 1),
+                    None => ("", 0),
+                }
+            } else {
+                let j = rest.find(|ch: char| !(ch.is_alphanumeric() || ch == '_')).unwrap_or(rest.len());
+                (&rest[..j], j)
+            };
+
+            if len == 0 {
+                dst.push('$');
+                continue;
+            }
+
+            let group = match name.parse::<usize>() {
+                Ok(index) => self.get(index),
+                Err(_) => self.name(name),
+            };
+            if let Some(group) = group {
+                dst.push_str(group.as_str());
+            }
+
+            rest = &rest[len..];
+        }
+
+        dst.push_str(rest);
+    }
+}
+
+pub trait Replacer {
+    fn replace_append(&mut self, caps: &Captures, dst: &mut String);
+}
+
+impl Replacer for &str {
+    fn replace_append(&mut self, caps: &Captures, dst: &mut String) {
+        caps.expand(self, dst);
+    }
+}
+
+impl Replacer for &String {
+    fn replace_append(&mut self, caps: &Captures, dst: &mut String) {
+        caps.expand(self, dst);
+    }
+}
+
+impl<F: FnMut(&Captures) -> String> Replacer for F {
+    fn replace_append(&mut self, caps: &Captures, dst: &mut String) {
+        dst.push_str(&self(caps));
+    }
+}
+
+pub struct Matches<'r, 't> {
+    regex: &'r Regex,
+    text: &'t str,
+    last_end: usize,
+    last_match: Option<usize>,
+}
+
+impl<'r, 't> Matches<'r, 't> {
+    pub fn new(regex: &'r Regex, text: &'t str) -> Matches<'r, 't> {
+        Matches { regex, text, last_end: 0, last_match: None }
+    }
+}
+
+impl<'r, 't> Matches<'r, 't> {
+    // An empty match right where the previous match ended is skipped, and
+    // the search after an empty match resumes one char later.
+    pub(crate) fn next_with<S: Steps>(&mut self, budget: &mut S) -> Result<Option<Match<'t>>, SearchError> {
+        loop {
+            if self.last_end > self.text.len() {
+                return Ok(None)
+            }
+
+            let m = match self.regex.find_at_with(self.text, self.last_end, budget)? {
+                Some(m) => m,
+                None => return Ok(None),
+            };
+
+            if m.is_empty() {
+                self.last_end = match self.text[m.end()..].chars().next() {
+                    Some(ch) => m.end() + ch.len_utf8(),
+                    None => m.end() + 1,
+                };
+
+                if Some(m.end()) == self.last_match {
+                    continue;
+                }
+            } else {
+                self.last_end = m.end();
+            }
+
+            self.last_match = Some(m.end());
+
+            return Ok(Some(m))
+        }
+    }
+}
+
+impl<'r, 't> Iterator for Matches<'r, 't> {
+    type Item = Match<'t>;
+
+    fn next(&mut self) -> Option<Match<'t>> {
+        self.next_with(&mut Unlimited).expect("Failed to find match")
+    }
+}
+
+// The matches of `Matches`, taking their steps from a budget. The error of
+// a search that runs out of budget is the last item.
+pub struct TryMatches<'r, 't, 'b, 'c> {
+    matches: Matches<'r, 't>,
+    budget: &'b mut Budget<'c>,
+    done: bool,
+}
+
+impl<'r, 't, 'b, 'c> TryMatches<'r, 't, 'b, 'c> {
+    pub fn new(regex: &'r Regex, text: &'t str, budget: &'b mut Budget<'c>) -> TryMatches<'r, 't, 'b, 'c> {
+        TryMatches { matches: Matches::new(regex, text), budget, done: false }
+    }
+}
+
+impl<'r, 't, 'b, 'c> Iterator for TryMatches<'r, 't, 'b, 'c> {
+    type Item = Result<Match<'t>, SearchError>;
+
+    fn next(&mut self) -> Option<Result<Match<'t>, SearchError>> {
+        if self.done {
+            return None
+        }
+
+        let res = self.matches.next_with(self.budget).transpose();
+        self.done = !matches!(res, Some(Ok(_)));
+
+        res
+    }
+}
+
+pub struct CaptureMatches<'r, 't> {
+    matches: Matches<'r, 't>,
+}
+
+impl<'r, 't> CaptureMatches<'r, 't> {
+    pub fn new(regex: &'r Regex, text: &'t str) -> CaptureMatches<'r, 't> {
+        CaptureMatches { matches: Matches::new(regex, text) }
+    }
+}
+
+impl<'r, 't> Iterator for CaptureMatches<'r, 't> {
+    type Item = Captures<'t>;
+
+    fn next(&mut self) -> Option<Captures<'t>> {
+        let m = self.matches.next()?;
+
+        Some(self.matches.regex.captures_of(m))
+    }
+}
+
+pub struct TryCaptureMatches<'r, 't, 'b, 'c> {
+    matches: TryMatches<'r, 't, 'b, 'c>,
+}
+
+impl<'r, 't, 'b, 'c> TryCaptureMatches<'r, 't, 'b, 'c> {
+    pub fn new(regex: &'r Regex, text: &'t str, budget: &'b mut Budget<'c>) -> TryCaptureMatches<'r, 't, 'b, 'c> {
+        TryCaptureMatches { matches: TryMatches::new(regex, text, budget) }
+    }
+}
+
+impl<'r, 't, 'b, 'c> Iterator for TryCaptureMatches<'r, 't, 'b, 'c> {
+    type Item = Result<Captures<'t>, SearchError>;
+
+    fn next(&mut self) -> Option<Result<Captures<'t>, SearchError>> {
+        let m = match self.matches.next()? {
+            Ok(m) => m,
+            Err(error) => return Some(Err(error)),
+        };
+
+        let caps = self.matches.matches.regex.captures_of_with(m, self.matches.budget);
+        self.matches.done = caps.is_err();
+
+        Some(caps)
+    }
+}
+
+pub struct Split<'r, 't> {
+    matches: Matches<'r, 't>,
+    last: usize,
+}
+
+impl<'r, 't> Split<'r, 't> {
+    pub fn new(regex: &'r Regex, text: &'t str) -> Split<'r, 't> {
+        Split { matches: Matches::new(regex, text), last: 0 }
+    }
+}
+
+impl<'r, 't> Split<'r, 't> {
+    pub(crate) fn next_with<S: Steps>(&mut self, budget: &mut S) -> Result<Option<&'t str>, SearchError> {
+        let text = self.matches.text;
+
+        Ok(match self.matches.next_with(budget)? {
+            Some(m) => {
+                let piece = &text[self.last..m.start()];
+                self.last = m.end();
+                Some(piece)
+            },
+            None => self.rest(),
+        })
+    }
+
+    // The text after the last match, once.
+    fn rest(&mut self) -> Option<&'t str> {
+        let text = self.matches.text;
+        if self.last > text.len() {
+            None
+        } else {
+            let piece = &text[self.last..];
+            self.last = text.len() + 1;
+            Some(piece)
+        }
+    }
+}
+
+impl<'r, 't> Iterator for Split<'r, 't> {
+    type Item = &'t str;
+
+    fn next(&mut self) -> Option<&'t str> {
+        self.next_with(&mut Unlimited).expect("Failed to split")
+    }
+}
+
+// The pieces of `Split`, taking their steps from a budget. The error of a
+// search that runs out of budget is the last item.
+pub struct TrySplit<'r, 't, 'b, 'c> {
+    split: Split<'r, 't>,
+    budget: &'b mut Budget<'c>,
+    done: bool,
+}
+
+impl<'r, 't, 'b, 'c> TrySplit<'r, 't, 'b, 'c> {
+    pub fn new(regex: &'r Regex, text: &'t str, budget: &'b mut Budget<'c>) -> TrySplit<'r, 't, 'b, 'c> {
+        TrySplit { split: Split::new(regex, text), budget, done: false }
+    }
+}
+
+impl<'r, 't, 'b, 'c> Iterator for TrySplit<'r, 't, 'b, 'c> {
+    type Item = Result<&'t str, SearchError>;
+
+    fn next(&mut self) -> Option<Result<&'t str, SearchError>> {
+        if self.done {
+            return None
+        }
+
+        let res = self.split.next_with(self.budget).transpose();
+        self.done = !matches!(res, Some(Ok(_)));
+
+        res
+    }
+}
+
+pub struct SplitN<'r, 't> {
+    split: Split<'r, 't>,
+    limit: usize,
+}
+
+impl<'r, 't> SplitN<'r, 't> {
+    pub fn new(regex: &'r Regex, text: &'t str, limit: usize) -> SplitN<'r, 't> {
+        SplitN { split: Split::new(regex, text), limit }
+    }
+}
+
+impl<'r, 't> SplitN<'r, 't> {
+    pub(crate) fn next_with<S: Steps>(&mut self, budget: &mut S) -> Result<Option<&'t str>, SearchError> {
+        if self.limit == 0 {
+            return Ok(None)
+        }
+
+        self.limit -= 1;
+        if self.limit > 0 {
+            return self.split.next_with(budget)
+        }
+
+        Ok(self.split.rest())
+    }
+}
+
+impl<'r, 't> Iterator for SplitN<'r, 't> {
+    type Item = &'t str;
+
+    fn next(&mut self) -> Option<&'t str> {
+        self.next_with(&mut Unlimited).expect("Failed to split")
+    }
+}
+
+// The pieces of `SplitN`, taking their steps from a budget.
+pub struct TrySplitN<'r, 't, 'b, 'c> {
+    splitn: SplitN<'r, 't>,
+    budget: &'b mut Budget<'c>,
+    done: bool,
+}
+
+impl<'r, 't, 'b, 'c> TrySplitN<'r, 't, 'b, 'c> {
+    pub fn new(regex: &'r Regex, text: &'t str, limit: usize, budget: &'b mut Budget<'c>) -> TrySplitN<'r, 't, 'b, 'c> {
+        TrySplitN { splitn: SplitN::new(regex, text, limit), budget, done: false }
+    }
+}
+
+impl<'r, 't, 'b, 'c> Iterator for TrySplitN<'r, 't, 'b, 'c> {
+    type Item = Result<&'t str, SearchError>;
+
+    fn next(&mut self) -> Option<Result<&'t str, SearchError>> {
+        if self.done {
+            return None
+        }
+
+        let res = self.splitn.next_with(self.budget).transpose();
+        self.done = !matches!(res, Some(Ok(_)));
+
+        res
+    }
+}
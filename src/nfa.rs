@@ -1,5 +1,14 @@
+use crate::dfa::*;
 use crate::fastate::*;
+use crate::error::BuildError;
 use unit::*;
+use std::collections::HashSet;
+
+// The tail accepts, and so does every state marked as a tail, which only
+// the nfas without epsilon trans have.
+pub(crate) fn is_final(states: &[FaState], i: usize) -> bool {
+    i == states.len() - 1 || matches!(states[i].kind, FaStateType::Tail)
+}
 
 #[derive(Clone)]
 pub struct Nfa {
@@ -148,6 +157,40 @@ impl Nfa {
         }
     }
 
+    fn from_capture(mut nfa_1: Nfa, index: usize) -> Nfa {
+        let len_1 = nfa_1.states.len();
+
+        let head = FaState {
+            index: 0,
+            trans: [Tran::Epsilon(1)].iter().cloned().collect(),
+            kind: FaStateType::CaptureStart(index),
+        };
+
+        let tail = FaState {
+            index: len_1 + 1,
+            trans: [].iter().cloned().collect(),
+            kind: FaStateType::CaptureEnd(index),
+        };
+
+        nfa_1.add_offset(1);
+
+        nfa_1.states[nfa_1.tail - 1].add_epsilon_tran(tail.index);
+
+        Nfa {
+            head: 0,
+            tail: tail.index,
+            states: {
+                let mut states: Vec<FaState> = Vec::new();
+
+                states.push(head);
+                states.append(&mut nfa_1.states);
+                states.push(tail);
+
+                states
+            }
+        }
+    }
+
     fn from_repeat_exact(nfa_1: Nfa, times: usize) -> Nfa {
         let len_1 = nfa_1.states.len();
 
@@ -253,19 +296,91 @@ impl Nfa {
         new_states.last_mut().unwrap().add_epsilon_tran(n * len_1 + 1);
         new_states.push(tail);
 
-        for i in m-1..n-1 {
+        // the tail of every copy from the m-th on, or the head when m is 0,
+        // may skip the copies after it
+        for i in m.max(1) - 1..n - 1 {
             let index = (i + 1) * len_1;
             new_states[index].add_epsilon_tran(n * len_1 + 1);
         }
+        if m == 0 {
+            new_states[0].add_epsilon_tran(n * len_1 + 1);
+        }
 
         Nfa {
             head: 0,
-            tail: 1 + len_1 * m,
+            tail: 1 + len_1 * n,
             states: new_states,
         }
     }
 
+    // Intersection and complement have no thompson construction, so the
+    // operands are turned into dfas, combined there, and the minimal result
+    // is put back as an nfa. Captures inside the operands are lost. The
+    // product of two dfas is bounded by the product of their sizes, which
+    // is what the limit is checked against.
+    fn from_intersection(nfa_1: Nfa, nfa_2: Nfa, dfa_limit: usize) -> Result<Nfa, BuildError> {
+        let dfa_1 = nfa_1.to_dfa(dfa_limit)?;
+        let dfa_2 = nfa_2.to_dfa(dfa_limit)?;
+        if dfa_1.states.len().saturating_mul(dfa_2.states.len()) > dfa_limit {
+            return Err(BuildError::DfaTooBig { limit: dfa_limit })
+        }
+
+        Ok(Nfa::from_dfa(&dfa_1.intersection(&dfa_2)))
+    }
+
+    fn from_complement(nfa_1: Nfa, dfa_limit: usize) -> Result<Nfa, BuildError> {
+        Ok(Nfa::from_dfa(&nfa_1.to_dfa(dfa_limit)?.complement()))
+    }
+
+    fn to_dfa(&self, limit: usize) -> Result<Dfa, BuildError> {
+        let mut chars: HashSet<Char> = HashSet::new();
+        for state in self.states.iter() {
+            for tran in state.trans.iter() {
+                if let Tran::Char(ch, _to) = tran {
+                    chars.insert(ch.clone());
+                }
+            }
+        }
+
+        Dfa::from_nfa_limited(&self.states, chars, limit)
+    }
+
+    // The states of the minimal dfa, head first, and a new tail reached by
+    // an epsilon from every accepting state.
+    fn from_dfa(dfa: &Dfa) -> Nfa {
+        let dfa = dfa.minimize();
+        let tail = dfa.states.len();
+
+        let mut states: Vec<FaState> = dfa.states.iter().map(|state| FaState {
+            index: state.index,
+            trans: state.trans.clone(),
+            kind: FaStateType::Normal,
+        }).collect();
+        for &accepting in dfa.tail.iter() {
+            states[accepting].add_epsilon_tran(tail);
+        }
+        states.push(FaState {
+            index: tail,
+            trans: HashSet::new(),
+            kind: FaStateType::Normal,
+        });
+
+        Nfa {
+            states,
+            head: dfa.head,
+            tail,
+        }
+    }
+
     pub fn from_expression(expression: &Vec<Unit>) -> Nfa {
+        Nfa::from_expression_limited(expression, usize::MAX, usize::MAX).expect("Failed to build nfa")
+    }
+
+    // Fails once the nfas built so far hold more than `limit` states. The
+    // copies a repeat makes are counted before they are made, so a pattern
+    // over the limit never takes much more memory than the limit. The dfas
+    // built for intersections and complements are held to `dfa_limit`.
+    pub fn from_expression_limited(expression: &[Unit], limit: usize, dfa_limit: usize) -> Result<Nfa, BuildError> {
         let units = expression;
         let mut nfas: Vec<Nfa> = Vec::new();
         for unit in units {
@@ -302,7 +417,27 @@ impl Nfa {
 
                     nfas.push(Nfa::from_alternation(nfa_1, nfa_2));
                 },
+                Unit::Operator(Operator::Intersection()) => {
+                    let nfa_2 = nfas.pop().expect("Failed to get nfa");
+                    let nfa_1 = nfas.pop().expect("Failed to get nfa");
+
+                    nfas.push(Nfa::from_intersection(nfa_1, nfa_2, dfa_limit)?);
+                },
+                Unit::Operator(Operator::Complement()) => {
+                    let nfa = nfas.pop().expect("Failed to get nfa");
+                    nfas.push(Nfa::from_complement(nfa, dfa_limit)?);
+                },
                 Unit::Operator(Operator::Repeat(repeat)) => {
+                    let copies = match repeat {
+                        Repeat::Exact(times) | Repeat::From(times) | Repeat::FromTo(_, times) => *times,
+                        Repeat::FromZero() | Repeat::Maybe() => 1,
+                    };
+                    let len = nfas.last().map_or(0, |nfa| nfa.states.len());
+                    let rest: usize = nfas.iter().map(|nfa| nfa.states.len()).sum::<usize>() - len;
+                    if rest.saturating_add(len.saturating_mul(copies)).saturating_add(2) > limit {
+                        return Err(BuildError::NfaTooBig { limit })
+                    }
+
                     match repeat {
                         Repeat::Exact(times) => {
                             let nfa = nfas.pop().expect("Failed to get nfa");
@@ -312,10 +447,18 @@ impl Nfa {
                             let nfa = nfas.pop().expect("Failed to get nfa");
                             nfas.push(Nfa::from_repeat_fromzero(nfa));
                         },
+                        Repeat::From(0) => {
+                            let nfa = nfas.pop().expect("Failed to get nfa");
+                            nfas.push(Nfa::from_repeat_fromzero(nfa));
+                        },
                         Repeat::From(from) => {
                             let nfa = nfas.pop().expect("Failed to get nfa");
                             nfas.push(Nfa::from_repeat_from(nfa, *from));
                         },
+                        Repeat::FromTo(_, 0) => {
+                            let nfa = nfas.pop().expect("Failed to get nfa");
+                            nfas.push(Nfa::from_repeat_exact(nfa, 0));
+                        },
                         Repeat::FromTo(from, to) => {
                             let nfa = nfas.pop().expect("Failed to get nfa");
                             nfas.push(Nfa::from_repeat_fromto(nfa, *from, *to));
@@ -326,13 +469,50 @@ impl Nfa {
                         }
                     }
                 },
+                Unit::Operator(Operator::Capture(index, _name)) => {
+                    let nfa = nfas.pop().expect("Failed to get nfa");
+                    nfas.push(Nfa::from_capture(nfa, *index));
+                },
                 _ => {
                     panic!("unexpected char");
                 },
             }
+
+            if nfas.iter().map(|nfa| nfa.states.len()).sum::<usize>() > limit {
+                return Err(BuildError::NfaTooBig { limit })
+            }
         }
 
-        nfas.pop().expect("Generate Nfa Error.")
+        Ok(nfas.pop().expect("Generate Nfa Error."))
+    }
+
+    pub fn reverse(&self) -> Nfa {
+        let len = self.states.len();
+
+        let mut states: Vec<FaState> = (0..len).map(|i| FaState {
+            index: i,
+            trans: HashSet::new(),
+            kind: FaStateType::Normal,
+        }).collect();
+
+        for state in self.states.iter() {
+            let from = len - 1 - state.index;
+            if state.index != self.tail && is_final(&self.states, state.index) {
+                states[len - 1 - self.tail].add_epsilon_tran(from);
+            }
+            for tran in state.trans.iter() {
+                match tran {
+                    Tran::Char(ch, to) => states[len - 1 - to].add_char_tran(ch.clone(), from),
+                    Tran::Epsilon(to) => states[len - 1 - to].add_epsilon_tran(from),
+                }
+            }
+        }
+
+        Nfa {
+            states,
+            head: len - 1 - self.tail,
+            tail: len - 1 - self.head,
+        }
     }
 
     pub fn to_string(&self) -> String{
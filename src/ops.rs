@@ -0,0 +1,237 @@
+use crate::alphabet::*;
+use crate::dfa::*;
+use crate::fastate::*;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use unit::*;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Intersection,
+    Union,
+    Difference,
+    SymmetricDifference,
+}
+
+// Builds a dfa from a table with one row per state and one column per
+// class, joining the classes that lead to the same target into one tran.
+fn from_table(classes: &[Char], head: usize, table: &[Vec<Option<usize>>], accepting: &[bool]) -> Dfa {
+    let mut dfa = Dfa {
+        head,
+        tail: vec![],
+        states: Vec::new(),
+    };
+
+    for (i, row) in table.iter().enumerate() {
+        let kind = if accepting[i] {
+            dfa.tail.push(i);
+            FaStateType::Tail
+        } else if i == head {
+            FaStateType::Head
+        } else {
+            FaStateType::Normal
+        };
+
+        let mut targets: Vec<(usize, Vec<(char, char)>)> = Vec::new();
+        for (class, to) in classes.iter().zip(row.iter()) {
+            if let Some(to) = *to {
+                match targets.iter_mut().find(|(to_, _ranges)| *to_ == to) {
+                    Some((_to, ranges)) => ranges.extend(class.to_ranges()),
+                    None => targets.push((to, class.to_ranges())),
+                }
+            }
+        }
+
+        let mut trans: HashSet<Tran> = HashSet::new();
+        for (to, ranges) in targets {
+            if let Some(ch) = Char::from_ranges(ranges) {
+                trans.insert(Tran::Char(ch, to));
+            }
+        }
+
+        dfa.states.push(FaState { index: i, trans, kind });
+    }
+
+    dfa
+}
+
+impl Dfa {
+    pub fn intersection(&self, other: &Dfa) -> Dfa {
+        self.product(other, Op::Intersection)
+    }
+
+    pub fn union(&self, other: &Dfa) -> Dfa {
+        self.product(other, Op::Union)
+    }
+
+    pub fn difference(&self, other: &Dfa) -> Dfa {
+        self.product(other, Op::Difference)
+    }
+
+    pub fn symmetric_difference(&self, other: &Dfa) -> Dfa {
+        self.product(other, Op::SymmetricDifference)
+    }
+
+    // Runs both dfas side by side over the classes of their joint alphabet.
+    // `None` stands for the dead state of a dfa that has no tran, so both
+    // are total without adding states to them.
+    fn product(&self, other: &Dfa, op: Op) -> Dfa {
+        let mut chars = self.get_chars();
+        chars.extend(other.get_chars());
+        let classes = classes(&chars);
+
+        let accept = |(a, b): (Option<usize>, Option<usize>)| {
+            let a = a.is_some_and(|a| self.is_accepted(a));
+            let b = b.is_some_and(|b| other.is_accepted(b));
+
+            match op {
+                Op::Intersection => a && b,
+                Op::Union => a || b,
+                Op::Difference => a && !b,
+                Op::SymmetricDifference => a != b,
+            }
+        };
+        let dead = |(a, b): (Option<usize>, Option<usize>)| match op {
+            Op::Intersection => a.is_none() || b.is_none(),
+            Op::Union => a.is_none() && b.is_none(),
+            Op::Difference => a.is_none(),
+            Op::SymmetricDifference => a.is_none() && b.is_none(),
+        };
+
+        let start = (Some(self.head), Some(other.head));
+        let mut pairs: Vec<(Option<usize>, Option<usize>)> = vec![start];
+        let mut map: HashMap<(Option<usize>, Option<usize>), usize> = HashMap::new();
+        map.insert(start, 0);
+        let mut table: Vec<Vec<Option<usize>>> = Vec::new();
+
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        queue.push_back(0);
+
+        while let Some(t) = queue.pop_front() {
+            let (a, b) = pairs[t];
+            let mut row: Vec<Option<usize>> = Vec::new();
+
+            for class in classes.iter() {
+                let ch = representative(class);
+                let u = (a.and_then(|a| self.step(a, ch)), b.and_then(|b| other.step(b, ch)));
+                if dead(u) {
+                    row.push(None);
+                    continue;
+                }
+
+                let u_id = match map.get(&u) {
+                    Some(&u_id) => u_id,
+                    None => {
+                        let u_id = pairs.len();
+                        pairs.push(u);
+                        map.insert(u, u_id);
+                        queue.push_back(u_id);
+                        u_id
+                    },
+                };
+                row.push(Some(u_id));
+            }
+
+            table.push(row);
+        }
+
+        let accepting: Vec<bool> = pairs.iter().map(|&pair| accept(pair)).collect();
+
+        from_table(&classes, 0, &table, &accepting)
+    }
+
+    // Makes the dfa total by sending every missing tran to a dead state that
+    // loops on all chars, then flips which states accept.
+    pub fn complement(&self) -> Dfa {
+        let classes = classes(&self.get_chars());
+        let dead = self.states.len();
+
+        let mut table: Vec<Vec<Option<usize>>> = self.states.iter().map(|state| {
+            classes.iter()
+                .map(|class| Some(self.step(state.index, representative(class)).unwrap_or(dead)))
+                .collect()
+        }).collect();
+        table.push(vec![Some(dead); classes.len()]);
+
+        let accepting: Vec<bool> = (0..=dead).map(|i| i == dead || !self.is_accepted(i)).collect();
+
+        from_table(&classes, self.head, &table, &accepting)
+    }
+
+    // Moore's partition refinement over the reachable states plus a dead
+    // state. Blocks equivalent to the dead state are dropped, and the rest are
+    // numbered in breadth first order from the head.
+    pub fn minimize(&self) -> Dfa {
+        let classes = classes(&self.get_chars());
+        let dead = self.states.len();
+
+        let mut table: Vec<Vec<usize>> = self.states.iter().map(|state| {
+            classes.iter()
+                .map(|class| self.step(state.index, representative(class)).unwrap_or(dead))
+                .collect()
+        }).collect();
+        table.push(vec![dead; classes.len()]);
+
+        let mut reachable: Vec<bool> = vec![false; dead + 1];
+        let mut stack: Vec<usize> = vec![self.head, dead];
+        while let Some(t) = stack.pop() {
+            if !reachable[t] {
+                reachable[t] = true;
+                stack.extend(table[t].iter().filter(|&&to| !reachable[to]));
+            }
+        }
+
+        let mut block: Vec<usize> = (0..=dead).map(|i| (i != dead && self.is_accepted(i)) as usize).collect();
+        let mut count = 0;
+        loop {
+            let mut ids: HashMap<(usize, Vec<usize>), usize> = HashMap::new();
+            let new_block: Vec<usize> = (0..=dead).map(|i| {
+                if !reachable[i] {
+                    return usize::MAX
+                }
+                let signature = (block[i], table[i].iter().map(|&to| block[to]).collect());
+                let len = ids.len();
+                *ids.entry(signature).or_insert(len)
+            }).collect();
+
+            block = new_block;
+            if ids.len() == count {
+                break;
+            }
+            count = ids.len();
+        }
+
+        let mut numbers: HashMap<usize, usize> = HashMap::new();
+        let mut members: Vec<usize> = Vec::new();
+        if block[self.head] != block[dead] {
+            numbers.insert(block[self.head], 0);
+            members.push(self.head);
+        }
+
+        let mut i = 0;
+        let mut new_table: Vec<Vec<Option<usize>>> = Vec::new();
+        while i < members.len() {
+            let row = table[members[i]].iter().map(|&to| {
+                if block[to] == block[dead] {
+                    return None
+                }
+                let len = numbers.len();
+                Some(*numbers.entry(block[to]).or_insert_with(|| {
+                    members.push(to);
+                    len
+                }))
+            }).collect();
+            new_table.push(row);
+            i += 1;
+        }
+
+        if new_table.is_empty() {
+            return from_table(&classes, 0, &[vec![None; classes.len()]], &[false])
+        }
+
+        let accepting: Vec<bool> = members.iter().map(|&member| self.is_accepted(member)).collect();
+
+        from_table(&classes, 0, &new_table, &accepting)
+    }
+}
@@ -0,0 +1,89 @@
+use crate::budget::*;
+use crate::fastate::*;
+use crate::nfa::*;
+
+fn add_thread(
+    list: &mut Vec<(usize, Vec<Option<usize>>)>,
+    seen: &mut [bool],
+    states: &[FaState],
+    state: usize,
+    slots: Vec<Option<usize>>,
+    pos: usize,
+) {
+    let mut stack: Vec<(usize, Vec<Option<usize>>)> = vec![(state, slots)];
+
+    while let Some((t, mut slots)) = stack.pop() {
+        if seen[t] {
+            continue;
+        }
+        seen[t] = true;
+
+        match states[t].kind {
+            FaStateType::CaptureStart(index) => slots[index * 2] = Some(pos),
+            FaStateType::CaptureEnd(index) => slots[index * 2 + 1] = Some(pos),
+            _ => {},
+        }
+
+        let mut targets: Vec<usize> = states[t].trans.iter().filter_map(|tran| match tran {
+            Tran::Epsilon(to) if !seen[*to] => Some(*to),
+            _ => None,
+        }).collect();
+        targets.sort();
+
+        for &to in targets.iter().rev() {
+            stack.push((to, slots.clone()));
+        }
+
+        list.push((t, slots));
+    }
+}
+
+// Runs the threads of the nfa in priority order over a match the dfas have
+// already located, and returns the capture slots of the best thread that
+// accepts exactly at `end`. Slot `2 * i` and `2 * i + 1` hold group `i`.
+pub fn captures(nfa: &Nfa, groups: usize, content: &str, start: usize, end: usize) -> Vec<Option<usize>> {
+    captures_with(nfa, groups, content, start, end, &mut Unlimited).expect("Failed to get captures")
+}
+
+// As `captures`, taking a step from the budget for every thread moved.
+pub fn try_captures(nfa: &Nfa, groups: usize, content: &str, start: usize, end: usize, budget: &mut Budget) -> Result<Vec<Option<usize>>, SearchError> {
+    captures_with(nfa, groups, content, start, end, budget)
+}
+
+pub(crate) fn captures_with<S: Steps>(nfa: &Nfa, groups: usize, content: &str, start: usize, end: usize, budget: &mut S) -> Result<Vec<Option<usize>>, SearchError> {
+    let len = nfa.states.len();
+
+    let mut clist: Vec<(usize, Vec<Option<usize>>)> = Vec::new();
+    add_thread(&mut clist, &mut vec![false; len], &nfa.states, nfa.head, vec![None; groups * 2], start);
+
+    for (i, ch) in content[start..end].char_indices() {
+        let pos = start + i + ch.len_utf8();
+        budget.step(clist.len().max(1))?;
+
+        let mut seen: Vec<bool> = vec![false; len];
+        let mut nlist: Vec<(usize, Vec<Option<usize>>)> = Vec::new();
+        for (t, slots) in clist.iter() {
+            let mut targets: Vec<usize> = nfa.states[*t].trans.iter().filter_map(|tran| match tran {
+                Tran::Char(ch_, to) if ch_.is_match(ch) => Some(*to),
+                _ => None,
+            }).collect();
+            targets.sort();
+
+            for to in targets {
+                add_thread(&mut nlist, &mut seen, &nfa.states, to, slots.clone(), pos);
+            }
+        }
+
+        clist = nlist;
+    }
+
+    let mut slots = clist.into_iter()
+        .find(|(t, _slots)| is_final(&nfa.states, *t))
+        .map(|(_t, slots)| slots)
+        .unwrap_or_else(|| vec![None; groups * 2]);
+
+    slots[0] = Some(start);
+    slots[1] = Some(end);
+
+    Ok(slots)
+}
@@ -1,7 +1,17 @@
 use crate::expression::Expression;
-use crate::fastate::*;
+use crate::glushkov::*;
+use crate::approx::*;
+use crate::budget::*;
 use crate::nfa::*;
 use crate::dfa::*;
+use crate::literal::*;
+use crate::matches::*;
+use crate::pikevm::*;
+use crate::stream::*;
+use crate::table::*;
+use rand::Rng;
+pub use crate::error::BuildError;
+use std::io::Read;
 
 
 
@@ -9,61 +19,200 @@ pub struct Regex {
     expression: Expression,
     nfa: Nfa,
     dfa: Dfa,
+    anchored: DenseDfa,
+    forward: DenseDfa,
+    searcher: DenseDfa,
+    reverse: DenseDfa,
+    prefilter: Option<Prefilter>,
+    required: Option<String>,
+    names: Vec<Option<String>>,
+    kind: MatchKind,
+}
+
+// Which nfa the search dfas are built from. The glushkov automaton has no
+// epsilon trans to close over, but it cannot hold an intersection or a
+// complement, and patterns using them are built from the thompson nfa.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NfaKind {
+    Thompson,
+    Glushkov,
+}
+
+pub struct RegexBuilder {
+    pattern: String,
+    kind: MatchKind,
+    nfa_kind: NfaKind,
+    size_limit: usize,
+    dfa_size_limit: usize,
+}
+
+impl RegexBuilder {
+    pub fn new(pattern: &str) -> RegexBuilder {
+        RegexBuilder {
+            pattern: pattern.to_string(),
+            kind: MatchKind::LeftmostLongest,
+            nfa_kind: NfaKind::Thompson,
+            size_limit: usize::MAX,
+            dfa_size_limit: usize::MAX,
+        }
+    }
+
+    pub fn match_kind(&mut self, kind: MatchKind) -> &mut RegexBuilder {
+        self.kind = kind;
+        self
+    }
+
+    pub fn nfa_kind(&mut self, nfa_kind: NfaKind) -> &mut RegexBuilder {
+        self.nfa_kind = nfa_kind;
+        self
+    }
+
+    // No limit is set unless asked for.
+    pub fn size_limit(&mut self, limit: usize) -> &mut RegexBuilder {
+        self.size_limit = limit;
+        self
+    }
+
+    pub fn dfa_size_limit(&mut self, limit: usize) -> &mut RegexBuilder {
+        self.dfa_size_limit = limit;
+        self
+    }
+
+    pub fn build(&self) -> Regex {
+        self.try_build().expect("Failed to build regex")
+    }
+
+    pub fn try_build(&self) -> Result<Regex, BuildError> {
+        let expression = Expression::try_from_str(&self.pattern)?;
+        let chars = expression.get_chars();
+        let limit = self.dfa_size_limit;
+        // the thompson nfa is kept either way for the captures
+        let nfa = Nfa::from_expression_limited(&expression.units, self.size_limit, limit)?;
+        let glushkov = match self.nfa_kind {
+            NfaKind::Glushkov => Glushkov::from_expression_limited(&expression.units, false, self.size_limit)?,
+            NfaKind::Thompson => None,
+        };
+
+        let (dfa, anchored, forward, searcher, reverse) = match &glushkov {
+            Some(glushkov) => {
+                let reversed = Glushkov::from_expression_limited(&expression.units, true, self.size_limit)?
+                    .expect("Failed to get reversed glushkov");
+                (
+                    Dfa::from_glushkov_limited(glushkov, limit)?,
+                    Dfa::from_threads(glushkov, glushkov.get_chars(), self.kind, true, false, limit)?,
+                    Dfa::from_threads(glushkov, glushkov.get_chars(), self.kind, false, false, limit)?,
+                    Dfa::from_threads(glushkov, glushkov.get_chars(), self.kind, false, true, limit)?,
+                    Dfa::from_glushkov_limited(&reversed, limit)?,
+                )
+            },
+            None => (
+                Dfa::from_nfa_limited(&nfa.states, chars.clone(), limit)?,
+                Dfa::from_threads(nfa.states.as_slice(), chars.clone(), self.kind, true, false, limit)?,
+                Dfa::from_threads(nfa.states.as_slice(), chars.clone(), self.kind, false, false, limit)?,
+                Dfa::from_threads(nfa.states.as_slice(), chars.clone(), self.kind, false, true, limit)?,
+                Dfa::from_nfa_limited(&nfa.reverse().states, chars, limit)?,
+            ),
+        };
+        let literals = Literals::from_expression(&expression.units);
+        let names = expression.get_group_names();
+
+        Ok(Regex {
+            expression,
+            nfa,
+            dfa,
+            anchored: DenseDfa::from_dfa(&anchored),
+            forward: DenseDfa::from_dfa(&forward),
+            searcher: DenseDfa::from_dfa(&searcher),
+            reverse: DenseDfa::from_dfa(&reverse),
+            prefilter: Prefilter::new(&literals.prefixes),
+            required: literals.required,
+            names,
+            kind: self.kind,
+        })
+    }
 }
 
 impl Regex {
     pub fn to_strings(&self) -> Vec<String> {
-        let mut res: Vec<String> = Vec::new();
+        vec![self.expression.to_string(), self.nfa.to_string(), self.dfa.to_string()]
+    }
 
-        res.push(self.expression.to_string());
-        res.push(self.nfa.to_string());
-        res.push(self.dfa.to_string());
+    pub fn from(raw_str: &str) -> Regex {
+        RegexBuilder::new(raw_str).build()
+    }
 
-        res
+    pub fn match_kind(&self) -> MatchKind {
+        self.kind
     }
 
-    pub fn from(raw_str: &String) -> Regex {
-        let expression = Expression::from_str(raw_str);
-        let chars = expression.get_chars();
-        let nfa = Nfa::from_expression(&expression.units);
-        let dfa = Dfa::from_nfa(&nfa.states, chars);
+    fn next_candidate(&self, content: &str, at: usize) -> Option<usize> {
+        if at >= content.len() {
+            return None
+        }
+
+        match &self.prefilter {
+            Some(prefilter) => prefilter.find(content, at),
+            None => Some(at),
+        }
+    }
 
+    fn next_char_boundary(content: &str, at: usize) -> usize {
+        at + content[at..].chars().next().map_or(1, |ch| ch.len_utf8())
+    }
 
-        Regex {
-            expression: expression,
-            nfa: nfa,
-            dfa: dfa,
+    fn may_match(&self, content: &str) -> bool {
+        match &self.required {
+            Some(required) => content.contains(required.as_str()),
+            None => true,
         }
     }
-    pub fn match_next_state(&self, cur_state: usize, ch: char) -> Option<usize> {
-        for tran in self.dfa.states[cur_state].trans.iter() {
-            match tran {
-                Tran::Char(ch_, to) => {
-                    if ch_.is_match(ch) {
-                        return Some(*to)
-                    }
-                },
-                Tran::Epsilon(_to) => panic!("epsilon tran should not be found in dfa"),
-            }
+
+    pub fn dfa(&self) -> &Dfa {
+        &self.dfa
+    }
+
+    // Both tell whether the languages of the two patterns agree, and if not
+    // give the shortest string that tells them apart.
+    pub fn is_equivalent(&self, other: &Regex) -> Result<(), String> {
+        match self.dfa.symmetric_difference(&other.dfa).shortest_match() {
+            Some(counterexample) => Err(counterexample),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_subset_of(&self, other: &Regex) -> Result<(), String> {
+        match self.dfa.difference(&other.dfa).shortest_match() {
+            Some(counterexample) => Err(counterexample),
+            None => Ok(()),
         }
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, max_len: usize) -> Option<String> {
+        self.dfa.sample(rng, max_len)
+    }
 
+    pub fn sample_uniform<R: Rng + ?Sized>(&self, rng: &mut R, len: usize) -> Option<String> {
+        self.dfa.sample_uniform(rng, len)
+    }
 
-        None
+    pub fn match_next_state(&self, cur_state: usize, ch: char) -> Option<usize> {
+        self.dfa.step(cur_state, ch)
     }
 
+    // With `is_greed` the end is picked by the match kind of the regex,
+    // otherwise the first accepting position is returned.
     pub fn matcher(&self, content: &str, is_greed : bool) -> Option<usize> {
-        let mut cur_state: usize = self.dfa.head;
+        let mut cur_state = self.anchored.start_state();
         let mut last_accepted_state: Option<usize> = None;
 
         for (i, ch) in content.chars().enumerate() {
-            if let Some(new_state) = self.match_next_state(cur_state, ch) {
-                cur_state = new_state;
-            } else {
+            cur_state = self.anchored.next_state(cur_state, ch);
+            if self.anchored.is_dead_state(cur_state) {
                 break;
             }
 
-            if let FaStateType::Tail = self.dfa.states[cur_state].kind {
-                if is_greed == false {
+            if self.anchored.is_match_state(cur_state) {
+                if !is_greed || self.kind == MatchKind::Earliest {
                     return Some(i)
                 }
                 last_accepted_state = Some(i)
@@ -73,57 +222,319 @@ impl Regex {
         last_accepted_state
     }
 
-    pub fn grep(&self, content: &str, is_greed : bool) -> Option<(usize, usize)> {
-        if content.len() == 0 {
-            return None
+    // Ends of the matches are found by one scan of the unanchored dfa, then
+    // the reversed dfa walks back from the end to the leftmost start. Every
+    // char read takes a step from the budget.
+    fn search<S: Steps>(&self, forward: &DenseDfa, content: &str, from: usize, budget: &mut S) -> Result<Option<(usize, usize)>, SearchError> {
+        let mut cur_state = forward.start_state();
+        let mut end: Option<usize> = None;
+        if forward.is_match_state(cur_state) {
+            end = Some(from);
         }
 
-        for i in 0..content.len() {
-            if let Some(match_index) = self.matcher(&content[i..], is_greed) {
-                return Some((i, i + match_index + 1))
+        if end.is_none() || self.kind != MatchKind::Earliest {
+            for (i, ch) in content[from..].char_indices() {
+                budget.step(1)?;
+                cur_state = forward.next_state(cur_state, ch);
+                if forward.is_dead_state(cur_state) {
+                    break;
+                }
+
+                if forward.is_match_state(cur_state) {
+                    end = Some(from + i + ch.len_utf8());
+                    if self.kind == MatchKind::Earliest {
+                        break;
+                    }
+                }
             }
         }
+        let end = match end {
+            Some(end) => end,
+            None => return Ok(None),
+        };
 
-        return None
+        Ok(Some((self.search_back(content, from, end, budget)?, end)))
     }
 
-    pub fn grep_all(&self, content: &str, is_greed : bool) -> Vec<(usize, usize)> {
-        let mut res: Vec<(usize, usize)> = Vec::new();
-
-        if content.len() == 0 {
-            return res
+    // Walks the reversed dfa back from the end of a match to its leftmost
+    // start, which is never before `from`.
+    pub(crate) fn search_back<S: Steps>(&self, content: &str, from: usize, end: usize, budget: &mut S) -> Result<usize, SearchError> {
+        let mut cur_state = self.reverse.start_state();
+        let mut start: Option<usize> = None;
+        if self.reverse.is_match_state(cur_state) {
+            start = Some(end);
         }
+        for (i, ch) in content[from..end].char_indices().rev() {
+            budget.step(1)?;
+            cur_state = self.reverse.next_state(cur_state, ch);
+            if self.reverse.is_dead_state(cur_state) {
+                break;
+            }
 
-        for i in 0..content.len() {
-            if let Some(match_index) = self.matcher(&content[i..], is_greed) {
-                res.push((i, i + match_index + 1));
+            if self.reverse.is_match_state(cur_state) {
+                start = Some(from + i);
             }
         }
 
-        return res
+        Ok(start.expect("reverse dfa should reach the match start"))
     }
 
-    pub fn grep_not_overlapped(&self, content: &str, is_greed : bool) -> Vec<(usize, usize)> {
-        let mut res: Vec<(usize, usize)> = Vec::new();
+    pub(crate) fn searcher(&self) -> &DenseDfa {
+        &self.searcher
+    }
 
-        if content.len() == 0 {
-            return res
+    fn grep_at<S: Steps>(&self, content: &str, at: usize, is_greed: bool, budget: &mut S) -> Result<Option<(usize, usize)>, SearchError> {
+        let from = match self.next_candidate(content, at) {
+            Some(from) => from,
+            None => return Ok(None),
+        };
+        let (start, end) = match self.search(&self.forward, content, from, budget)? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+        if is_greed {
+            return Ok(Some((start, end)))
         }
 
-        for i in 0..content.len() {
-            if let Some((_a, b)) = res.last() {
-                if i < *b {
-                    continue;
-                }
+        let mut cur_state = self.anchored.start_state();
+        for (i, ch) in content[start..end].char_indices() {
+            budget.step(1)?;
+            cur_state = self.anchored.next_state(cur_state, ch);
+            if self.anchored.is_dead_state(cur_state) {
+                return Ok(None)
             }
 
-            if let Some(match_index) = self.matcher(&content[i..], is_greed) {
-                res.push((i, i + match_index + 1));
+            if self.anchored.is_match_state(cur_state) {
+                return Ok(Some((start, start + i + ch.len_utf8())))
+            }
+        }
+
+        Ok(Some((start, end)))
+    }
+
+    pub fn find<'t>(&self, content: &'t str) -> Option<Match<'t>> {
+        self.find_at(content, 0)
+    }
+
+    pub fn find_at<'t>(&self, content: &'t str, at: usize) -> Option<Match<'t>> {
+        self.find_at_with(content, at, &mut Unlimited).expect("Failed to find match")
+    }
+
+    // The `try_` searches give up with an error once the budget runs out.
+    pub fn try_find<'t>(&self, content: &'t str, budget: &mut Budget) -> Result<Option<Match<'t>>, SearchError> {
+        self.try_find_at(content, 0, budget)
+    }
+
+    pub fn try_find_at<'t>(&self, content: &'t str, at: usize, budget: &mut Budget) -> Result<Option<Match<'t>>, SearchError> {
+        self.find_at_with(content, at, budget)
+    }
+
+    pub(crate) fn find_at_with<'t, S: Steps>(&self, content: &'t str, at: usize, budget: &mut S) -> Result<Option<Match<'t>>, SearchError> {
+        let from = match &self.prefilter {
+            Some(_prefilter) => match self.next_candidate(content, at) {
+                Some(from) => from,
+                None => return Ok(None),
+            },
+            None => at,
+        };
+
+        Ok(self.search(&self.searcher, content, from, budget)?
+            .map(|(start, end)| Match::new(content, start, end)))
+    }
+
+    // The match needing the fewest insertions, deletions and substitutions,
+    // at most `max_edits`, with that number.
+    pub fn find_approx<'t>(&self, content: &'t str, max_edits: usize) -> Option<(Match<'t>, usize)> {
+        find_approx(&self.nfa, content, max_edits)
+            .map(|(start, end, edits)| (Match::new(content, start, end), edits))
+    }
+
+    pub fn try_find_approx<'t>(&self, content: &'t str, max_edits: usize, budget: &mut Budget) -> Result<Option<(Match<'t>, usize)>, SearchError> {
+        Ok(try_find_approx(&self.nfa, content, max_edits, budget)?
+            .map(|(start, end, edits)| (Match::new(content, start, end), edits)))
+    }
+
+    pub fn stream_find_iter<R: Read>(&self, reader: R) -> StreamMatches<'_, R> {
+        StreamMatches::new(self, reader)
+    }
+
+    // As `stream_find_iter`, taking its steps from the budget. A budget that
+    // runs out ends the matches with an error holding the `SearchError`.
+    pub fn try_stream_find_iter<'b, 'c, R: Read>(&self, reader: R, budget: &'b mut Budget<'c>) -> TryStreamMatches<'_, 'b, 'c, R> {
+        TryStreamMatches::new(self, reader, budget)
+    }
+
+    pub fn find_iter<'r, 't>(&'r self, content: &'t str) -> Matches<'r, 't> {
+        Matches::new(self, content)
+    }
+
+    pub fn try_find_iter<'r, 't, 'b, 'c>(&'r self, content: &'t str, budget: &'b mut Budget<'c>) -> TryMatches<'r, 't, 'b, 'c> {
+        TryMatches::new(self, content, budget)
+    }
+
+    pub fn captures<'t>(&self, content: &'t str) -> Option<Captures<'t>> {
+        self.find(content).map(|m| self.captures_of(m))
+    }
+
+    pub fn try_captures<'t>(&self, content: &'t str, budget: &mut Budget) -> Result<Option<Captures<'t>>, SearchError> {
+        let m = match self.try_find(content, budget)? {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+
+        Ok(Some(self.captures_of_with(m, budget)?))
+    }
+
+    pub fn captures_iter<'r, 't>(&'r self, content: &'t str) -> CaptureMatches<'r, 't> {
+        CaptureMatches::new(self, content)
+    }
+
+    pub fn try_captures_iter<'r, 't, 'b, 'c>(&'r self, content: &'t str, budget: &'b mut Budget<'c>) -> TryCaptureMatches<'r, 't, 'b, 'c> {
+        TryCaptureMatches::new(self, content, budget)
+    }
+
+    pub(crate) fn captures_of<'t>(&self, m: Match<'t>) -> Captures<'t> {
+        self.captures_of_with(m, &mut Unlimited).expect("Failed to get captures")
+    }
+
+    pub(crate) fn captures_of_with<'t, S: Steps>(&self, m: Match<'t>, budget: &mut S) -> Result<Captures<'t>, SearchError> {
+        let slots = captures_with(&self.nfa, self.names.len(), m.text(), m.start(), m.end(), budget)?;
+
+        Ok(Captures::new(m.text(), slots, &self.names))
+    }
+
+    pub fn group_names(&self) -> &[Option<String>] {
+        &self.names
+    }
+
+    pub fn split<'r, 't>(&'r self, content: &'t str) -> Split<'r, 't> {
+        Split::new(self, content)
+    }
+
+    pub fn try_split<'r, 't, 'b, 'c>(&'r self, content: &'t str, budget: &'b mut Budget<'c>) -> TrySplit<'r, 't, 'b, 'c> {
+        TrySplit::new(self, content, budget)
+    }
+
+    pub fn splitn<'r, 't>(&'r self, content: &'t str, limit: usize) -> SplitN<'r, 't> {
+        SplitN::new(self, content, limit)
+    }
+
+    pub fn try_splitn<'r, 't, 'b, 'c>(&'r self, content: &'t str, limit: usize, budget: &'b mut Budget<'c>) -> TrySplitN<'r, 't, 'b, 'c> {
+        TrySplitN::new(self, content, limit, budget)
+    }
+
+    pub fn replace<R: Replacer>(&self, content: &str, rep: R) -> String {
+        self.replacen(content, 1, rep)
+    }
+
+    pub fn replace_all<R: Replacer>(&self, content: &str, rep: R) -> String {
+        self.replacen(content, 0, rep)
+    }
+
+    // Replaces the first `limit` matches, or all of them when `limit` is 0.
+    pub fn replacen<R: Replacer>(&self, content: &str, limit: usize, rep: R) -> String {
+        self.replacen_with(content, limit, rep, &mut Unlimited).expect("Failed to replace")
+    }
+
+    pub fn try_replace<R: Replacer>(&self, content: &str, rep: R, budget: &mut Budget) -> Result<String, SearchError> {
+        self.replacen_with(content, 1, rep, budget)
+    }
+
+    pub fn try_replace_all<R: Replacer>(&self, content: &str, rep: R, budget: &mut Budget) -> Result<String, SearchError> {
+        self.replacen_with(content, 0, rep, budget)
+    }
+
+    pub fn try_replacen<R: Replacer>(&self, content: &str, limit: usize, rep: R, budget: &mut Budget) -> Result<String, SearchError> {
+        self.replacen_with(content, limit, rep, budget)
+    }
+
+    fn replacen_with<R: Replacer, S: Steps>(&self, content: &str, limit: usize, mut rep: R, budget: &mut S) -> Result<String, SearchError> {
+        let mut res = String::new();
+        let mut last = 0;
+
+        let mut matches = Matches::new(self, content);
+        let mut i = 0;
+        while let Some(m) = matches.next_with(budget)? {
+            if limit > 0 && i >= limit {
+                break;
             }
+
+            let caps = self.captures_of_with(m, budget)?;
+            res.push_str(&content[last..m.start()]);
+            rep.replace_append(&caps, &mut res);
+            last = m.end();
+            i += 1;
         }
 
-        return res
+        res.push_str(&content[last..]);
+
+        Ok(res)
     }
 
+    pub fn grep(&self, content: &str, is_greed : bool) -> Option<(usize, usize)> {
+        if content.is_empty() || !self.may_match(content) {
+            return None
+        }
+
+        self.grep_at(content, 0, is_greed, &mut Unlimited).expect("Failed to grep")
+    }
+
+    pub fn try_grep(&self, content: &str, is_greed : bool, budget: &mut Budget) -> Result<Option<(usize, usize)>, SearchError> {
+        if content.is_empty() || !self.may_match(content) {
+            return Ok(None)
+        }
+
+        self.grep_at(content, 0, is_greed, budget)
+    }
+
+    pub fn grep_all(&self, content: &str, is_greed : bool) -> Vec<(usize, usize)> {
+        self.grep_all_with(content, is_greed, &mut Unlimited).expect("Failed to grep")
+    }
+
+    // Each match is searched for from the char after the start of the one
+    // before, so the steps can grow with the square of the text.
+    pub fn try_grep_all(&self, content: &str, is_greed : bool, budget: &mut Budget) -> Result<Vec<(usize, usize)>, SearchError> {
+        self.grep_all_with(content, is_greed, budget)
+    }
 
+    fn grep_all_with<S: Steps>(&self, content: &str, is_greed : bool, budget: &mut S) -> Result<Vec<(usize, usize)>, SearchError> {
+        let mut res: Vec<(usize, usize)> = Vec::new();
+
+        if content.is_empty() || !self.may_match(content) {
+            return Ok(res)
+        }
+
+        let mut i = 0;
+        while let Some((start, end)) = self.grep_at(content, i, is_greed, budget)? {
+            res.push((start, end));
+            i = Regex::next_char_boundary(content, start);
+        }
+
+        Ok(res)
+    }
+
+    pub fn grep_not_overlapped(&self, content: &str, is_greed : bool) -> Vec<(usize, usize)> {
+        self.grep_not_overlapped_with(content, is_greed, &mut Unlimited).expect("Failed to grep")
+    }
+
+    pub fn try_grep_not_overlapped(&self, content: &str, is_greed : bool, budget: &mut Budget) -> Result<Vec<(usize, usize)>, SearchError> {
+        self.grep_not_overlapped_with(content, is_greed, budget)
+    }
+
+    fn grep_not_overlapped_with<S: Steps>(&self, content: &str, is_greed : bool, budget: &mut S) -> Result<Vec<(usize, usize)>, SearchError> {
+        let mut res: Vec<(usize, usize)> = Vec::new();
+
+        if content.is_empty() || !self.may_match(content) {
+            return Ok(res)
+        }
+
+        let mut i = 0;
+        while let Some((start, end)) = self.grep_at(content, i, is_greed, budget)? {
+            res.push((start, end));
+            i = end;
+        }
+
+        Ok(res)
+    }
 }
\ No newline at end of file
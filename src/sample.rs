@@ -0,0 +1,84 @@
+use crate::dfa::*;
+use crate::enumerate::*;
+use rand::Rng;
+
+// The `offset`-th char from `a` on, surrogates left out.
+fn nth_char(a: char, offset: u64) -> char {
+    let mut code = a as u64 + offset;
+    if (a as u64) < 0xD800 && code >= 0xD800 {
+        code += 0x800;
+    }
+
+    std::char::from_u32(code as u32).expect("offset is inside the range")
+}
+
+impl Dfa {
+    // Random walk from the head. At each state the walk picks evenly between
+    // stopping, when the state accepts, and each range that still reaches an
+    // accepting state within the length left, then a char of that range.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, max_len: usize) -> Option<String> {
+        let ranges = self.sorted_ranges();
+
+        // within[k][s]: an accepting state is at most k chars away from s
+        let mut within: Vec<Vec<bool>> = vec![(0..self.states.len()).map(|i| self.is_accepted(i)).collect()];
+        for k in 1..=max_len {
+            let row = ranges.iter().enumerate().map(|(s, ranges)| {
+                within[k - 1][s] || ranges.iter().any(|&(_a, _b, to)| within[k - 1][to])
+            }).collect();
+            within.push(row);
+        }
+
+        if !within[max_len][self.head] {
+            return None
+        }
+
+        let mut ans = String::new();
+        let mut state = self.head;
+        for rest in (0..max_len).rev() {
+            let moves: Vec<&(char, char, usize)> = ranges[state].iter().filter(|&&(_a, _b, to)| within[rest][to]).collect();
+            let stop = self.is_accepted(state) as usize;
+
+            let pick = rng.gen_range(0..stop + moves.len());
+            if pick < stop {
+                return Some(ans)
+            }
+
+            let &(a, b, to) = moves[pick - stop];
+            ans.push(nth_char(a, rng.gen_range(0..range_len(a, b))));
+            state = to;
+        }
+
+        Some(ans)
+    }
+
+    // Picks each accepted string of exactly `len` chars with the same
+    // probability, following the path counts back from the accepting states.
+    // Counts saturate at `u64::MAX`, past which the choice is only close to
+    // uniform.
+    pub fn sample_uniform<R: Rng + ?Sized>(&self, rng: &mut R, len: usize) -> Option<String> {
+        let ways = self.count_paths(len);
+        let ranges = self.sorted_ranges();
+
+        if ways[len][self.head] == 0 {
+            return None
+        }
+
+        let mut ans = String::new();
+        let mut state = self.head;
+        for rest in (0..len).rev() {
+            let mut pick = rng.gen_range(0..ways[rest + 1][state]);
+
+            for &(a, b, to) in ranges[state].iter() {
+                let weight = range_len(a, b).saturating_mul(ways[rest][to]);
+                if pick < weight {
+                    ans.push(nth_char(a, (pick / ways[rest][to]).min(range_len(a, b) - 1)));
+                    state = to;
+                    break;
+                }
+                pick -= weight;
+            }
+        }
+
+        Some(ans)
+    }
+}
@@ -0,0 +1,313 @@
+use crate::dfa::*;
+use crate::fastate::*;
+use crate::table::*;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+use unit::*;
+
+// Layout of a serialized dfa, every field a u32 in the byte order of the
+// machine that wrote it:
+//
+//   magic "LEXDFA\0\0" (two words), endianness mark 0x0102_0304, version,
+//   state count, class count, range count, start state,
+//   128 ascii class ids,
+//   range count pairs (first char, class id), sorted by first char,
+//   state count accepting flags,
+//   state count * class count targets, `DEAD` for no tran.
+const MAGIC: &[u8; 8] = b"LEXDFA\0\0";
+const ENDIANNESS: u32 = 0x0102_0304;
+const VERSION: u32 = 1;
+const HEADER_WORDS: usize = 8;
+const DEAD: u32 = u32::MAX;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeserializeError {
+    BadMagic,
+    WrongEndianness,
+    UnsupportedVersion(u32),
+    BadLength { expected: usize, found: usize },
+    Invalid(&'static str),
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeserializeError::BadMagic => write!(f, "not a serialized dfa"),
+            DeserializeError::WrongEndianness => write!(f, "dfa was serialized with another byte order"),
+            DeserializeError::UnsupportedVersion(version) => write!(f, "unsupported dfa format version {}", version),
+            DeserializeError::BadLength { expected, found } => write!(f, "expected {} bytes of dfa, found {}", expected, found),
+            DeserializeError::Invalid(reason) => write!(f, "invalid dfa: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+// A count or index of the dfa that does not fit in a word of the format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerializeError {
+    TooBig { field: &'static str, value: usize },
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SerializeError::TooBig { field, value } => write!(f, "dfa {} {} does not fit in the format", field, value),
+        }
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+fn to_word(field: &'static str, value: usize) -> Result<u32, SerializeError> {
+    u32::try_from(value).map_err(|_err| SerializeError::TooBig { field, value })
+}
+
+// A state index, which must also stay clear of `DEAD`. Holding the state
+// count below `DEAD` keeps every index below it.
+fn to_state(field: &'static str, value: usize) -> Result<u32, SerializeError> {
+    match to_word(field, value)? {
+        DEAD => Err(SerializeError::TooBig { field, value }),
+        word => Ok(word),
+    }
+}
+
+pub(crate) fn encode(dense: &DenseDfa) -> Result<Vec<u8>, SerializeError> {
+    let classes = &dense.classes;
+    let states = dense.accepting.len();
+
+    let mut words: Vec<u32> = vec![
+        u32::from_ne_bytes([MAGIC[0], MAGIC[1], MAGIC[2], MAGIC[3]]),
+        u32::from_ne_bytes([MAGIC[4], MAGIC[5], MAGIC[6], MAGIC[7]]),
+        ENDIANNESS,
+        VERSION,
+        to_state("state count", states)?,
+        to_word("class count", classes.len())?,
+        to_word("range count", classes.starts.len())?,
+        to_state("start state", dense.start.0)?,
+    ];
+    for &id in classes.ascii.iter() {
+        words.push(to_word("class id", id)?);
+    }
+    for (&start, &id) in classes.starts.iter().zip(classes.ids.iter()) {
+        words.push(start as u32);
+        words.push(to_word("class id", id)?);
+    }
+    words.extend(dense.accepting.iter().map(|&accepting| accepting as u32));
+    for to in dense.table.iter() {
+        words.push(match to.index() {
+            Some(index) => to_state("state index", index)?,
+            None => DEAD,
+        });
+    }
+
+    Ok(words.iter().flat_map(|word| word.to_ne_bytes()).collect())
+}
+
+// A serialized dfa used in place. The bytes are checked once when the view
+// is made, so stepping it afterwards never goes out of bounds.
+#[derive(Clone, Copy)]
+pub struct DfaRef<'a> {
+    states: usize,
+    classes: usize,
+    start: usize,
+    ascii: &'a [u8],
+    ranges: &'a [u8],
+    accepting: &'a [u8],
+    table: &'a [u8],
+}
+
+fn word(bytes: &[u8], i: usize) -> u32 {
+    u32::from_ne_bytes([bytes[i * 4], bytes[i * 4 + 1], bytes[i * 4 + 2], bytes[i * 4 + 3]])
+}
+
+impl<'a> DfaRef<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<DfaRef<'a>, DeserializeError> {
+        if bytes.len() < 8 || &bytes[..8] != MAGIC {
+            return Err(DeserializeError::BadMagic)
+        }
+        if bytes.len() < HEADER_WORDS * 4 {
+            return Err(DeserializeError::BadLength { expected: HEADER_WORDS * 4, found: bytes.len() })
+        }
+
+        let endianness = word(bytes, 2);
+        if endianness != ENDIANNESS {
+            return Err(if endianness == ENDIANNESS.swap_bytes() {
+                DeserializeError::WrongEndianness
+            } else {
+                DeserializeError::Invalid("bad endianness mark")
+            })
+        }
+        let version = word(bytes, 3);
+        if version != VERSION {
+            return Err(DeserializeError::UnsupportedVersion(version))
+        }
+
+        let states = word(bytes, 4) as usize;
+        let classes = word(bytes, 5) as usize;
+        let range_count = word(bytes, 6) as usize;
+        let start = word(bytes, 7) as usize;
+
+        let words = states.checked_mul(classes)
+            .and_then(|cells| cells.checked_add(states))
+            .and_then(|n| n.checked_add(range_count.checked_mul(2)?))
+            .and_then(|n| n.checked_add(HEADER_WORDS + 128))
+            .and_then(|n| n.checked_mul(4));
+        let expected = words.ok_or(DeserializeError::Invalid("sizes overflow"))?;
+        if bytes.len() != expected {
+            return Err(DeserializeError::BadLength { expected, found: bytes.len() })
+        }
+
+        let (ascii, rest) = bytes[HEADER_WORDS * 4..].split_at(128 * 4);
+        let (ranges, rest) = rest.split_at(range_count * 8);
+        let (accepting, table) = rest.split_at(states * 4);
+
+        let dfa = DfaRef { states, classes, start, ascii, ranges, accepting, table };
+        dfa.validate()?;
+
+        Ok(dfa)
+    }
+
+    fn validate(&self) -> Result<(), DeserializeError> {
+        if self.states == 0 || self.start >= self.states {
+            return Err(DeserializeError::Invalid("start state out of range"))
+        }
+        if self.classes == 0 {
+            return Err(DeserializeError::Invalid("no char classes"))
+        }
+
+        let range_count = self.ranges.len() / 8;
+        if range_count == 0 || word(self.ranges, 0) != 0 {
+            return Err(DeserializeError::Invalid("ranges do not start at the first char"))
+        }
+        for i in 0..range_count {
+            let start = word(self.ranges, i * 2);
+            if std::char::from_u32(start).is_none() {
+                return Err(DeserializeError::Invalid("range starts with an invalid char"))
+            }
+            if i > 0 && start <= word(self.ranges, i * 2 - 2) {
+                return Err(DeserializeError::Invalid("ranges are not sorted"))
+            }
+            if word(self.ranges, i * 2 + 1) as usize >= self.classes {
+                return Err(DeserializeError::Invalid("class id out of range"))
+            }
+        }
+
+        for b in 0..128u8 {
+            if word(self.ascii, b as usize) as usize != self.range_class(b as char) {
+                return Err(DeserializeError::Invalid("ascii classes disagree with ranges"))
+            }
+        }
+
+        for i in 0..self.states {
+            if word(self.accepting, i) > 1 {
+                return Err(DeserializeError::Invalid("accepting flag is not 0 or 1"))
+            }
+        }
+        for i in 0..self.states * self.classes {
+            let to = word(self.table, i);
+            if to != DEAD && to as usize >= self.states {
+                return Err(DeserializeError::Invalid("tran target out of range"))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn range_class(&self, ch: char) -> usize {
+        let count = self.ranges.len() / 8;
+        let (mut lo, mut hi) = (0, count);
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            if word(self.ranges, mid * 2) <= ch as u32 {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        word(self.ranges, lo * 2 + 1) as usize
+    }
+
+    fn class(&self, ch: char) -> usize {
+        if ch.is_ascii() {
+            word(self.ascii, ch as usize) as usize
+        } else {
+            self.range_class(ch)
+        }
+    }
+
+    pub fn start_state(&self) -> StateId {
+        StateId(self.start)
+    }
+
+    pub fn next_state(&self, state: StateId, ch: char) -> StateId {
+        match state.index() {
+            Some(index) => match word(self.table, index * self.classes + self.class(ch)) {
+                DEAD => StateId::DEAD,
+                to => StateId(to as usize),
+            },
+            None => StateId::DEAD,
+        }
+    }
+
+    pub fn is_match_state(&self, state: StateId) -> bool {
+        state.index().is_some_and(|index| word(self.accepting, index) == 1)
+    }
+
+    pub fn is_dead_state(&self, state: StateId) -> bool {
+        state == StateId::DEAD
+    }
+
+    // Rebuilds an owned dfa, joining the classes that lead from one state to
+    // the same target into one tran.
+    pub fn to_dfa(&self) -> Dfa {
+        let range_count = self.ranges.len() / 8;
+        let mut class_ranges: Vec<Vec<(char, char)>> = vec![Vec::new(); self.classes];
+        for i in 0..range_count {
+            let a = std::char::from_u32(word(self.ranges, i * 2)).expect("validated");
+            let b = if i + 1 < range_count {
+                let next = std::char::from_u32(word(self.ranges, i * 2 + 2)).expect("validated");
+                prev_char(next).expect("ranges are sorted")
+            } else {
+                std::char::MAX
+            };
+            class_ranges[word(self.ranges, i * 2 + 1) as usize].push((a, b));
+        }
+
+        let mut dfa = Dfa {
+            head: self.start,
+            tail: vec![],
+            states: (0..self.states).map(|i| FaState {
+                index: i,
+                trans: HashSet::new(),
+                kind: FaStateType::Normal,
+            }).collect(),
+        };
+
+        for i in 0..self.states {
+            let mut targets: HashMap<usize, Vec<(char, char)>> = HashMap::new();
+            for ranges in class_ranges.iter().filter(|ranges| !ranges.is_empty()) {
+                if let Some(to) = self.next_state(StateId(i), ranges[0].0).index() {
+                    targets.entry(to).or_default().extend(ranges.iter().cloned());
+                }
+            }
+            for (to, ranges) in targets {
+                if let Some(ch) = Char::from_ranges(ranges) {
+                    dfa.states[i].add_char_tran(ch, to);
+                }
+            }
+
+            if self.is_match_state(StateId(i)) {
+                dfa.states[i].kind = FaStateType::Tail;
+                dfa.tail.push(i);
+            } else if i == self.start {
+                dfa.states[i].kind = FaStateType::Head;
+            }
+        }
+
+        dfa
+    }
+}
@@ -0,0 +1,161 @@
+use crate::fastate::*;
+use crate::nfa::*;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use unit::*;
+
+fn target(tran: &Tran) -> usize {
+    match tran {
+        Tran::Char(_, to) | Tran::Epsilon(to) => *to,
+    }
+}
+
+// These passes keep the strings an nfa accepts, not the priorities between
+// its threads nor the capture marks on its epsilon paths, so what they give
+// is for building full match dfas and for reading.
+impl Nfa {
+    // Every state takes over the char trans of the states its epsilon
+    // closure reaches, and is marked as a tail when that closure holds a
+    // final state. The old tail stays last, so the result is still an nfa
+    // everything else can take.
+    pub fn remove_epsilons(&self) -> Nfa {
+        let len = self.states.len();
+
+        let states = (0..len).map(|i| {
+            let mut seen: Vec<bool> = vec![false; len];
+            let mut stack: Vec<usize> = vec![i];
+            seen[i] = true;
+
+            let mut trans: HashSet<Tran> = HashSet::new();
+            let mut accepting = false;
+            while let Some(t) = stack.pop() {
+                accepting = accepting || is_final(&self.states, t);
+
+                for tran in self.states[t].trans.iter() {
+                    match tran {
+                        Tran::Char(_ch, _to) => {
+                            trans.insert(tran.clone());
+                        },
+                        Tran::Epsilon(to) => {
+                            if !seen[*to] {
+                                seen[*to] = true;
+                                stack.push(*to);
+                            }
+                        },
+                    }
+                }
+            }
+
+            FaState {
+                index: i,
+                trans,
+                kind: if accepting { FaStateType::Tail } else { FaStateType::Normal },
+            }
+        }).collect();
+
+        Nfa {
+            states,
+            head: self.head,
+            tail: self.tail,
+        }
+    }
+
+    // Keeps the head, the tail and the states on a path from the head to a
+    // final state, numbered in their old order.
+    pub fn trim(&self) -> Nfa {
+        let len = self.states.len();
+
+        let mut reachable: Vec<bool> = vec![false; len];
+        let mut stack: Vec<usize> = vec![self.head];
+        reachable[self.head] = true;
+        while let Some(t) = stack.pop() {
+            for tran in self.states[t].trans.iter() {
+                let to = target(tran);
+                if !reachable[to] {
+                    reachable[to] = true;
+                    stack.push(to);
+                }
+            }
+        }
+
+        let mut sources: Vec<Vec<usize>> = vec![Vec::new(); len];
+        for state in self.states.iter() {
+            for tran in state.trans.iter() {
+                sources[target(tran)].push(state.index);
+            }
+        }
+
+        let mut useful: Vec<bool> = (0..len).map(|i| is_final(&self.states, i)).collect();
+        let mut stack: Vec<usize> = (0..len).filter(|&i| useful[i]).collect();
+        while let Some(t) = stack.pop() {
+            for &from in sources[t].iter() {
+                if !useful[from] {
+                    useful[from] = true;
+                    stack.push(from);
+                }
+            }
+        }
+
+        let mut numbers: Vec<Option<usize>> = vec![None; len];
+        let mut kept: Vec<usize> = Vec::new();
+        for i in 0..len {
+            if i == self.head || i == self.tail || (reachable[i] && useful[i]) {
+                numbers[i] = Some(kept.len());
+                kept.push(i);
+            }
+        }
+
+        let states = kept.iter().enumerate().map(|(index, &i)| FaState {
+            index,
+            trans: self.states[i].trans.iter().filter_map(|tran| match tran {
+                Tran::Char(ch, to) => numbers[*to].map(|to| Tran::Char(ch.clone(), to)),
+                Tran::Epsilon(to) => numbers[*to].map(Tran::Epsilon),
+            }).collect(),
+            kind: self.states[i].kind.clone(),
+        }).collect();
+
+        Nfa {
+            states,
+            head: numbers[self.head].expect("the head is kept"),
+            tail: numbers[self.tail].expect("the tail is kept"),
+        }
+    }
+
+    // Joins the char trans of a state that lead to the same state into one.
+    pub fn merge_trans(&self) -> Nfa {
+        let states = self.states.iter().map(|state| {
+            let mut ranges: HashMap<usize, Vec<(char, char)>> = HashMap::new();
+            let mut trans: HashSet<Tran> = HashSet::new();
+
+            for tran in state.trans.iter() {
+                match tran {
+                    Tran::Char(ch, to) => ranges.entry(*to).or_default().extend(ch.to_ranges()),
+                    Tran::Epsilon(_to) => {
+                        trans.insert(tran.clone());
+                    },
+                }
+            }
+            for (to, ranges) in ranges {
+                if let Some(ch) = Char::from_ranges(ranges) {
+                    trans.insert(Tran::Char(ch, to));
+                }
+            }
+
+            FaState {
+                index: state.index,
+                trans,
+                kind: state.kind.clone(),
+            }
+        }).collect();
+
+        Nfa {
+            states,
+            head: self.head,
+            tail: self.tail,
+        }
+    }
+
+    pub fn simplify(&self) -> Nfa {
+        self.remove_epsilons().trim().merge_trans()
+    }
+}
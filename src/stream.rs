@@ -0,0 +1,220 @@
+use crate::budget::*;
+use crate::dfa::*;
+use crate::table::*;
+use crate::regex::Regex;
+use std::io;
+use std::io::Read;
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+// Finds the matches of a regex in a stream read chunk by chunk, with the same
+// semantics as `Regex::find_iter`. Offsets are bytes from the start of the
+// stream. Only the text from the oldest thread still alive is kept around, so
+// memory stays small unless a partial match spans a large part of the input.
+pub struct StreamMatches<'r, R: Read> {
+    regex: &'r Regex,
+    reader: R,
+    eof: bool,
+    done: bool,
+    buffer: String,
+    offset: usize,
+    pending: Vec<u8>,
+    from: usize,
+    scanned: usize,
+    cur_state: StateId,
+    end: Option<usize>,
+    skip: bool,
+    last_match: Option<usize>,
+}
+
+impl<'r, R: Read> StreamMatches<'r, R> {
+    pub fn new(regex: &'r Regex, reader: R) -> StreamMatches<'r, R> {
+        let mut matches = StreamMatches {
+            regex,
+            reader,
+            eof: false,
+            done: false,
+            buffer: String::new(),
+            offset: 0,
+            pending: Vec::new(),
+            from: 0,
+            scanned: 0,
+            cur_state: StateId::DEAD,
+            end: None,
+            skip: false,
+            last_match: None,
+        };
+        matches.restart(0);
+
+        matches
+    }
+
+    fn restart(&mut self, at: usize) {
+        let dfa = self.regex.searcher();
+
+        self.from = at;
+        self.scanned = at;
+        self.cur_state = dfa.start_state();
+        self.end = if dfa.is_match_state(self.cur_state) { Some(at) } else { None };
+        self.skip = false;
+    }
+
+    fn char_at(&self, at: usize) -> Option<char> {
+        self.buffer[at - self.offset..].chars().next()
+    }
+
+    // Reads the next chunk, keeping the bytes of a char cut at its end for
+    // the chunk after it.
+    fn fill(&mut self) -> io::Result<()> {
+        let keep = self.from - self.offset;
+        if keep > 0 {
+            self.buffer.drain(..keep);
+            self.offset += keep;
+        }
+
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let n = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(n) => break n,
+                Err(ref error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        };
+
+        if n == 0 {
+            self.eof = true;
+            if !self.pending.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "stream ends inside a utf-8 sequence"))
+            }
+            return Ok(())
+        }
+
+        self.pending.extend_from_slice(&chunk[..n]);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(error) => {
+                if error.error_len().is_some() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, error))
+                }
+                error.valid_up_to()
+            },
+        };
+
+        let text = std::str::from_utf8(&self.pending[..valid]).expect("checked above");
+        self.buffer.push_str(text);
+        self.pending.drain(..valid);
+
+        Ok(())
+    }
+
+    fn next_match<S: Steps>(&mut self, budget: &mut S) -> io::Result<Option<(usize, usize)>> {
+        let dfa: &DenseDfa = self.regex.searcher();
+        let earliest = self.regex.match_kind() == MatchKind::Earliest;
+
+        loop {
+            if self.skip {
+                match self.char_at(self.scanned) {
+                    Some(ch) => self.restart(self.scanned + ch.len_utf8()),
+                    None if self.eof => return Ok(None),
+                    None => {
+                        self.fill()?;
+                        continue;
+                    },
+                }
+            }
+
+            let mut dead = earliest && self.end.is_some();
+            while !dead {
+                let ch = match self.char_at(self.scanned) {
+                    Some(ch) => ch,
+                    None => break,
+                };
+
+                budget.step(1).map_err(io::Error::other)?;
+                self.cur_state = dfa.next_state(self.cur_state, ch);
+                if dfa.is_dead_state(self.cur_state) {
+                    dead = true;
+                    break;
+                }
+                self.scanned += ch.len_utf8();
+
+                if dfa.is_match_state(self.cur_state) {
+                    self.end = Some(self.scanned);
+                    dead = earliest;
+                } else if self.end.is_none() && self.cur_state == dfa.start_state() {
+                    self.from = self.scanned;
+                }
+            }
+
+            if !dead && !self.eof {
+                self.fill()?;
+                continue;
+            }
+
+            let end = match self.end {
+                Some(end) => end,
+                None => return Ok(None),
+            };
+            let start = self.offset + self.regex.search_back(&self.buffer, self.from - self.offset, end - self.offset, budget).map_err(io::Error::other)?;
+
+            self.restart(end);
+            if start == end {
+                self.skip = true;
+                if Some(end) == self.last_match {
+                    continue;
+                }
+            }
+            self.last_match = Some(end);
+
+            return Ok(Some((start, end)))
+        }
+    }
+
+    fn next_with<S: Steps>(&mut self, budget: &mut S) -> Option<io::Result<(usize, usize)>> {
+        if self.done {
+            return None
+        }
+
+        match self.next_match(budget) {
+            Ok(Some(m)) => Some(Ok(m)),
+            Ok(None) => {
+                self.done = true;
+                None
+            },
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            },
+        }
+    }
+}
+
+impl<'r, R: Read> Iterator for StreamMatches<'r, R> {
+    type Item = io::Result<(usize, usize)>;
+
+    fn next(&mut self) -> Option<io::Result<(usize, usize)>> {
+        self.next_with(&mut Unlimited)
+    }
+}
+
+// The matches of `StreamMatches`, taking their steps from a budget. A budget
+// that runs out ends them with an `io::Error` whose inner error is the
+// `SearchError`.
+pub struct TryStreamMatches<'r, 'b, 'c, R: Read> {
+    matches: StreamMatches<'r, R>,
+    budget: &'b mut Budget<'c>,
+}
+
+impl<'r, 'b, 'c, R: Read> TryStreamMatches<'r, 'b, 'c, R> {
+    pub fn new(regex: &'r Regex, reader: R, budget: &'b mut Budget<'c>) -> TryStreamMatches<'r, 'b, 'c, R> {
+        TryStreamMatches { matches: StreamMatches::new(regex, reader), budget }
+    }
+}
+
+impl<'r, 'b, 'c, R: Read> Iterator for TryStreamMatches<'r, 'b, 'c, R> {
+    type Item = io::Result<(usize, usize)>;
+
+    fn next(&mut self) -> Option<io::Result<(usize, usize)>> {
+        self.matches.next_with(self.budget)
+    }
+}
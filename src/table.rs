@@ -0,0 +1,171 @@
+use crate::alphabet::*;
+use crate::dfa::*;
+use crate::fastate::*;
+
+// Maps every char to the equivalence class it falls in. Ascii chars are
+// looked up directly, the rest by a binary search over the class ranges.
+pub struct CharClasses {
+    pub(crate) ascii: Vec<usize>,
+    pub(crate) starts: Vec<char>,
+    pub(crate) ids: Vec<usize>,
+    representatives: Vec<char>,
+}
+
+impl CharClasses {
+    pub fn from_dfa(dfa: &Dfa) -> CharClasses {
+        let classes = classes(&dfa.get_chars());
+
+        let mut ranges: Vec<(char, usize)> = Vec::new();
+        for (id, class) in classes.iter().enumerate() {
+            for (a, _b) in class.to_ranges() {
+                ranges.push((a, id));
+            }
+        }
+        ranges.sort();
+
+        let starts: Vec<char> = ranges.iter().map(|&(a, _id)| a).collect();
+        let ids: Vec<usize> = ranges.iter().map(|&(_a, id)| id).collect();
+        let ascii: Vec<usize> = (0..128u8)
+            .map(|b| ids[starts.partition_point(|&a| a <= b as char) - 1])
+            .collect();
+
+        CharClasses {
+            ascii,
+            starts,
+            ids,
+            representatives: classes.iter().map(representative).collect(),
+        }
+    }
+
+    pub fn get(&self, ch: char) -> usize {
+        if ch.is_ascii() {
+            self.ascii[ch as usize]
+        } else {
+            self.ids[self.starts.partition_point(|&a| a <= ch) - 1]
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.representatives.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.representatives.is_empty()
+    }
+}
+
+// A dfa compiled into one row per state and one column per char class, so
+// a step is a class lookup and a single index into the table.
+pub struct DenseDfa {
+    pub(crate) classes: CharClasses,
+    pub(crate) table: Vec<StateId>,
+    pub(crate) accepting: Vec<bool>,
+    pub(crate) start: StateId,
+}
+
+impl DenseDfa {
+    pub fn from_dfa(dfa: &Dfa) -> DenseDfa {
+        let classes = CharClasses::from_dfa(dfa);
+
+        let mut table: Vec<StateId> = Vec::with_capacity(dfa.states.len() * classes.len());
+        for state in dfa.states.iter() {
+            for &ch in classes.representatives.iter() {
+                table.push(dfa.next_state(StateId(state.index), ch));
+            }
+        }
+
+        DenseDfa {
+            classes,
+            table,
+            accepting: (0..dfa.states.len()).map(|i| dfa.is_accepted(i)).collect(),
+            start: dfa.start_state(),
+        }
+    }
+
+    pub fn start_state(&self) -> StateId {
+        self.start
+    }
+
+    pub fn next_state(&self, state: StateId, ch: char) -> StateId {
+        match state.index() {
+            Some(index) => self.table[index * self.classes.len() + self.classes.get(ch)],
+            None => StateId::DEAD,
+        }
+    }
+
+    pub fn is_match_state(&self, state: StateId) -> bool {
+        state.index().is_some_and(|index| self.accepting[index])
+    }
+
+    pub fn is_dead_state(&self, state: StateId) -> bool {
+        state == StateId::DEAD
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.table.len() * std::mem::size_of::<StateId>()
+            + self.accepting.len()
+            + (self.classes.ascii.len() + self.classes.starts.len() + self.classes.ids.len()) * std::mem::size_of::<usize>()
+    }
+}
+
+// A dfa keeping only the trans each state has, as sorted char ranges. It
+// needs no class table, which suits patterns over big alphabets where most
+// states only move on a few ranges.
+pub struct SparseDfa {
+    trans: Vec<Vec<(char, char, StateId)>>,
+    accepting: Vec<bool>,
+    start: StateId,
+}
+
+impl SparseDfa {
+    pub fn from_dfa(dfa: &Dfa) -> SparseDfa {
+        let trans = dfa.states.iter().map(|state| {
+            let mut ranges: Vec<(char, char, StateId)> = Vec::new();
+            for tran in state.trans.iter() {
+                if let Tran::Char(ch, to) = tran {
+                    for (a, b) in ch.to_ranges() {
+                        ranges.push((a, b, StateId(*to)));
+                    }
+                }
+            }
+            ranges.sort_by_key(|&(a, _b, _to)| a);
+
+            ranges
+        }).collect();
+
+        SparseDfa {
+            trans,
+            accepting: (0..dfa.states.len()).map(|i| dfa.is_accepted(i)).collect(),
+            start: dfa.start_state(),
+        }
+    }
+
+    pub fn start_state(&self) -> StateId {
+        self.start
+    }
+
+    pub fn next_state(&self, state: StateId, ch: char) -> StateId {
+        let ranges = match state.index() {
+            Some(index) => &self.trans[index],
+            None => return StateId::DEAD,
+        };
+
+        match ranges.get(ranges.partition_point(|&(_a, b, _to)| b < ch)) {
+            Some(&(a, _b, to)) if a <= ch => to,
+            _ => StateId::DEAD,
+        }
+    }
+
+    pub fn is_match_state(&self, state: StateId) -> bool {
+        state.index().is_some_and(|index| self.accepting[index])
+    }
+
+    pub fn is_dead_state(&self, state: StateId) -> bool {
+        state == StateId::DEAD
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.trans.iter().map(|ranges| ranges.len()).sum::<usize>() * std::mem::size_of::<(char, char, StateId)>()
+            + self.accepting.len()
+    }
+}
@@ -44,6 +44,32 @@ impl Char {
         }
     }
 
+    pub fn to_ranges(&self) -> Vec<(char, char)> {
+        let mut ranges: Vec<(char, char)> = match self {
+            Char::Single(a) => vec![(*a, *a)],
+            Char::Set(set) => set.iter().map(|ch| (*ch, *ch)).collect(),
+            Char::Range(a, b) => vec![(*a, *b)],
+            Char::Ranges(ranges) => ranges.clone(),
+            Char::Not(a, b) => complement(&[(*a, *b)]),
+            Char::But(a, b, c, d) => intersect(&[(*a, *b)], &complement(&[(*c, *d)])),
+        };
+
+        normalize(&mut ranges);
+
+        ranges
+    }
+
+    pub fn from_ranges(mut ranges: Vec<(char, char)>) -> Option<Char> {
+        normalize(&mut ranges);
+
+        match ranges.len() {
+            0 => None,
+            1 if ranges[0].0 == ranges[0].1 => Some(Char::Single(ranges[0].0)),
+            1 => Some(Char::Range(ranges[0].0, ranges[0].1)),
+            _ => Some(Char::Ranges(ranges)),
+        }
+    }
+
     pub fn from_str(raw_str: &str) -> Option<Char> {
         if let Ok(char_class) = scan_fmt!(raw_str ,"[:{}:]" , String) {
             match &char_class[..] {
@@ -94,9 +120,13 @@ impl Char {
             Char::Ranges(ranges) => {
                 let mut res = String::from("[");
                 for (a, b) in ranges {
-                    res += &format!("{}-{},", *a, *b);
+                    if a == b {
+                        res.push(*a);
+                    } else {
+                        res += &format!("{}-{}", *a, *b);
+                    }
                 }
-                res += "}";
+                res += "]";
 
                 res
             }
@@ -104,6 +134,76 @@ impl Char {
     }
 }
 
+pub fn next_char(ch: char) -> Option<char> {
+    match ch {
+        '\u{D7FF}' => Some('\u{E000}'),
+        _ => std::char::from_u32(ch as u32 + 1),
+    }
+}
+
+pub fn prev_char(ch: char) -> Option<char> {
+    match ch {
+        '\u{E000}' => Some('\u{D7FF}'),
+        '\0' => None,
+        _ => std::char::from_u32(ch as u32 - 1),
+    }
+}
+
+fn normalize(ranges: &mut Vec<(char, char)>) {
+    ranges.retain(|(a, b)| a <= b);
+    ranges.sort();
+
+    let mut res: Vec<(char, char)> = Vec::new();
+    for &(a, b) in ranges.iter() {
+        if let Some(last) = res.last_mut() {
+            if next_char(last.1).is_none_or(|next| a <= next) {
+                if b > last.1 {
+                    last.1 = b;
+                }
+                continue;
+            }
+        }
+        res.push((a, b));
+    }
+
+    *ranges = res;
+}
+
+fn complement(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut ranges = ranges.to_vec();
+    normalize(&mut ranges);
+
+    let mut res: Vec<(char, char)> = Vec::new();
+    let mut from = Some('\0');
+    for (a, b) in ranges {
+        if let (Some(from), Some(to)) = (from, prev_char(a)) {
+            if from <= to {
+                res.push((from, to));
+            }
+        }
+        from = next_char(b);
+    }
+
+    if let Some(from) = from {
+        res.push((from, std::char::MAX));
+    }
+
+    res
+}
+
+fn intersect(lhs: &[(char, char)], rhs: &[(char, char)]) -> Vec<(char, char)> {
+    let mut res: Vec<(char, char)> = Vec::new();
+    for &(a, b) in lhs {
+        for &(c, d) in rhs {
+            if a.max(c) <= b.min(d) {
+                res.push((a.max(c), b.min(d)));
+            }
+        }
+    }
+
+    res
+}
+
 #[derive(PartialEq, Clone)]
 pub enum Repeat {
     Exact(usize),
@@ -145,11 +245,14 @@ impl Repeat {
 
 #[derive(PartialEq, Clone)]
 pub enum Operator {
-    LeftParenthese(),
+    LeftParenthese(Option<String>),
     RightParenthese(),
     Alternation(),
+    Intersection(),
     Concatenation(),
+    Complement(),
     Repeat(Repeat),
+    Capture(usize, Option<String>),
 }
 
 #[derive(PartialEq, Clone)]
@@ -162,11 +265,14 @@ impl Unit {
     pub fn get_pivot(&self) -> u32 {
         match self {
             Unit::Operator(op) => match op {
-                Operator::LeftParenthese()       => 1,
+                Operator::LeftParenthese(_name)  => 1,
                 Operator::RightParenthese()      => 2,
                 Operator::Alternation()          => 3,
-                Operator::Concatenation()        => 4,
-                Operator::Repeat(_rp)            => 5,
+                Operator::Intersection()         => 4,
+                Operator::Concatenation()        => 5,
+                Operator::Complement()           => 6,
+                Operator::Repeat(_rp)            => 7,
+                Operator::Capture(_index, _name) => 7,
             },
             Unit::Char(_ch) => 0,
         }
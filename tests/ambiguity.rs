@@ -0,0 +1,54 @@
+use lexer::ambiguity::*;
+use lexer::regex::*;
+
+fn ambiguities(patterns: &[&str]) -> Ambiguities {
+    let rules: Vec<Regex> = patterns.iter().map(|pattern| Regex::from(pattern)).collect();
+    Ambiguities::from_rules(&rules)
+}
+
+#[test]
+fn disjoint_rules_have_no_conflicts() {
+    assert!(ambiguities(&["[a-z]+", "[0-9]+", "=", "==?"]).shadowed.is_empty());
+    assert!(ambiguities(&["[a-z]+", "[0-9]+", "[(]", " +"]).is_empty());
+}
+
+#[test]
+fn keywords_overlap_the_identifier_rule() {
+    let found = ambiguities(&["if", "else", "[a-z]+", "[0-9]+"]);
+
+    assert_eq!(found.overlaps, vec![
+        Overlap { first: 0, second: 2, examples: vec![String::from("if")] },
+        Overlap { first: 1, second: 2, examples: vec![String::from("else")] },
+    ]);
+    assert_eq!(found.shadowed, vec![]);
+}
+
+#[test]
+fn later_keywords_are_shadowed_by_the_identifier_rule() {
+    let found = ambiguities(&["[a-z]+", "if", "[0-9]+", "0|1", "x&y"]);
+
+    assert_eq!(found.shadowed, vec![
+        Shadowed { rule: 1, by: vec![0], examples: vec![String::from("if")] },
+        Shadowed { rule: 3, by: vec![2], examples: vec![String::from("0"), String::from("1")] },
+        Shadowed { rule: 4, by: vec![], examples: vec![] },
+    ]);
+}
+
+#[test]
+fn a_rule_can_be_shadowed_by_several_rules_together() {
+    let found = ambiguities(&["a[a-m]*", "a[n-z]*", "a[a-z]", "b"]);
+
+    assert_eq!(found.shadowed, vec![
+        Shadowed { rule: 2, by: vec![0, 1], examples: vec![String::from("aa"), String::from("ab"), String::from("ac")] },
+    ]);
+    assert_eq!(found.overlaps[0], Overlap { first: 0, second: 1, examples: vec![String::from("a")] });
+}
+
+#[test]
+fn conflicts_render_one_per_line() {
+    let names = ["[a-z]+", "if", "[0-9]+"];
+    assert_eq!(ambiguities(&names).render(&names), concat!(
+        "rules 0 `[a-z]+` and 1 `if` both match \"if\"\n",
+        "rule 1 `if` never fires, it is shadowed by 0 `[a-z]+`, e.g. on \"if\"",
+    ));
+}
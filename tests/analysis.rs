@@ -0,0 +1,44 @@
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::nfa::Nfa;
+
+fn build(pattern: &str) -> Dfa {
+    let expression = Expression::from_str(&pattern.to_string());
+    let nfa = Nfa::from_expression(&expression.units);
+
+    Dfa::from_nfa(&nfa.states, expression.get_chars())
+}
+
+#[test]
+fn empty_languages_are_found() {
+    assert!(!build("a").is_empty());
+    assert!(!build("a*").is_empty());
+    assert!(build("a").intersection(&build("b")).is_empty());
+    assert!(build("[a-z]+").difference(&build("[a-z]*")).is_empty());
+    assert!(!build("[a-z]+").difference(&build("if")).is_empty());
+}
+
+#[test]
+fn finite_languages_have_a_longest_match() {
+    assert!(build("if|else|while").is_finite());
+    assert_eq!(build("if|else|while").longest_match_length(), Some(5));
+    assert_eq!(build("a{2,4}b?").longest_match_length(), Some(5));
+    assert_eq!(build("(é|日本)?").longest_match_length(), Some(2));
+
+    assert!(!build("ab*c").is_finite());
+    assert_eq!(build("ab*c").longest_match_length(), None);
+    assert_eq!(build("a").intersection(&build("b")).longest_match_length(), None);
+
+    // the loop cannot be left towards an accepting state
+    let dfa = build("ab|ac*d").difference(&build("ac*d"));
+    assert!(dfa.is_finite());
+    assert_eq!(dfa.longest_match_length(), Some(2));
+}
+
+#[test]
+fn shortest_match_is_first_in_shortlex_order() {
+    assert_eq!(build("c|b|ab").shortest_match(), Some(String::from("b")));
+    assert_eq!(build("a*").shortest_match(), Some(String::from("")));
+    assert_eq!(build("(x|y)z+").shortest_match(), Some(String::from("xz")));
+    assert_eq!(build("a").intersection(&build("b")).shortest_match(), None);
+}
@@ -0,0 +1,37 @@
+use lexer::regex::*;
+
+fn approx(pattern: &str, text: &str, max_edits: usize) -> Option<(usize, usize, usize)> {
+    Regex::from(pattern)
+        .find_approx(text, max_edits)
+        .map(|(m, edits)| (m.start(), m.end(), edits))
+}
+
+#[test]
+fn finds_words_with_typos() {
+    let regex = Regex::from(&String::from("identifier"));
+    let (found, edits) = regex.find_approx("an identifer here", 2).expect("a match within two edits");
+
+    assert_eq!((found.as_str(), edits), ("identifer", 1));
+    assert_eq!(approx("identifier", "an idnetifier", 1), None);
+    assert_eq!(approx("identifier", "an idnetifier", 2), Some((3, 13, 2)));
+}
+
+#[test]
+fn fewer_edits_win_over_leftmost() {
+    assert_eq!(approx("abc", "abx abc", 1), Some((4, 7, 0)));
+    assert_eq!(approx("abc", "abx abd", 1), Some((0, 3, 1)));
+    assert_eq!(approx("abc", "xyz", 2), None);
+    assert_eq!(approx("abc", "xyz", 3), Some((0, 3, 3)));
+}
+
+#[test]
+fn no_edits_is_the_leftmost_longest_match() {
+    for pattern in ["[a-z]+[0-9]", "(a|ab)(c|bcd)", "é+x?"].iter() {
+        let regex = Regex::from(pattern);
+        for text in ["abc1 x9", "abcd", "ééxé", "12"].iter() {
+            let lhs = regex.find(text).map(|m| (m.start(), m.end()));
+            let rhs = regex.find_approx(text, 0).map(|(m, _edits)| (m.start(), m.end()));
+            assert_eq!(lhs, rhs, "{} on {}", pattern, text);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use lexer::bitparallel::BitParallel;
+use lexer::regex::*;
+
+fn bits(pattern: &str) -> BitParallel {
+    BitParallel::new(pattern).expect("Failed to get bit parallel matcher")
+}
+
+#[test]
+fn find_agrees_with_the_dfa_path() {
+    let patterns = ["[a-z]+[0-9]*", "(a|b)*abb", "if|else|while", "(é|日本)+x?", "a*", "(ab|a)(c|bcd)", "x{2,3}y?"];
+    let texts = ["", "abc12 x9", "aababb", "else if", "日本é日x", "xxxxy", "abcd"];
+
+    for pattern in patterns.iter() {
+        let regex = RegexBuilder::new(pattern).build();
+        let matcher = bits(pattern);
+
+        for text in texts.iter() {
+            let lhs = regex.find(text).map(|m| (m.start(), m.end()));
+            let rhs = matcher.find(text).map(|m| (m.start(), m.end()));
+            assert_eq!(lhs, rhs, "{} on {}", pattern, text);
+            assert_eq!(matcher.is_match(text), lhs.is_some(), "{} on {}", pattern, text);
+            assert_eq!(matcher.accepts(text), regex.dfa().accepts(text), "{} on {}", pattern, text);
+        }
+    }
+}
+
+#[test]
+fn find_at_skips_earlier_matches() {
+    let matcher = bits("[0-9]+");
+
+    assert_eq!(matcher.find_at("a1 b22 c333", 3).map(|m| m.as_str()), Some("22"));
+    assert_eq!(matcher.find_at("a1 b22 c333", 5).map(|m| m.as_str()), Some("2"));
+    assert_eq!(matcher.find_at("a1 b22 c333", 11), None);
+}
+
+#[test]
+fn at_most_sixty_four_positions() {
+    assert!(BitParallel::new("a{64}").is_some());
+    assert!(BitParallel::new("a{65}").is_none());
+    assert!(BitParallel::new("[a-z]+&~(if)").is_none());
+
+    let matcher = bits("[ab]{32}c{31}d");
+    assert!(matcher.accepts(&format!("{}{}d", "ab".repeat(16), "c".repeat(31))));
+    assert!(!matcher.accepts(&format!("{}{}d", "ab".repeat(16), "c".repeat(30))));
+}
@@ -0,0 +1,158 @@
+use lexer::budget::*;
+use lexer::regex::*;
+use std::cell::Cell;
+use std::time::Duration;
+use std::time::Instant;
+
+fn regex(pattern: &str) -> Regex {
+    Regex::from(pattern)
+}
+
+#[test]
+fn searches_within_the_budget_agree_with_the_plain_ones() {
+    let text = "let x1 = foo(bar_2, 33);";
+    for pattern in ["[a-z]+[0-9]", "(?P<f>[a-z]+)[(]", "[0-9]+"].iter() {
+        let regex = regex(pattern);
+        let mut budget = Budget::new();
+        budget.max_steps(10_000);
+
+        assert_eq!(regex.try_find(text, &mut budget), Ok(regex.find(text)));
+        assert_eq!(regex.try_grep_all(text, true, &mut budget), Ok(regex.grep_all(text, true)));
+        assert_eq!(regex.try_find_approx(text, 1, &mut budget), Ok(regex.find_approx(text, 1)));
+
+        let caps = regex.try_captures(text, &mut budget).expect("within the budget").expect("a match");
+        let expected = regex.captures(text).expect("a match");
+        assert_eq!(caps.get(1).map(|m| m.as_str()), expected.get(1).map(|m| m.as_str()));
+    }
+}
+
+#[test]
+fn steps_add_up_over_searches() {
+    let regex = regex("abc");
+    let mut budget = Budget::new();
+
+    assert_eq!(regex.try_find("xxabcxx", &mut budget).map(|m| m.map(|m| m.range())), Ok(Some(2..5)));
+    let once = budget.steps();
+    assert!(once >= 5, "{}", once);
+
+    regex.try_find("xxabcxx", &mut budget).expect("no limit");
+    assert_eq!(budget.steps(), 2 * once);
+}
+
+#[test]
+fn quadratic_grep_all_stops_at_the_step_limit() {
+    // every match is `a`, and each search reads on to the end looking for `c`
+    let text = "a".repeat(20_000);
+    let mut budget = Budget::new();
+    budget.max_steps(100_000);
+
+    assert_eq!(regex("[ab]*c|a").try_grep_all(&text, true, &mut budget), Err(SearchError::StepLimit { limit: 100_000 }));
+    assert!(budget.steps() <= 100_001);
+}
+
+#[test]
+fn approximate_matching_stops_at_the_step_limit() {
+    let text = "x".repeat(10_000);
+    let mut budget = Budget::new();
+    budget.max_steps(1000);
+
+    assert_eq!(regex("hello world").try_find_approx(&text, 3, &mut budget), Err(SearchError::StepLimit { limit: 1000 }));
+}
+
+#[test]
+fn cancelled_and_timed_out_searches_fail() {
+    let text = "ab".repeat(100_000);
+
+    let calls = Cell::new(0);
+    let cancel = || {
+        calls.set(calls.get() + 1);
+        calls.get() > 3
+    };
+    let mut budget = Budget::new();
+    budget.cancel_with(&cancel);
+    assert_eq!(regex("(a|b)*c").try_find(&text, &mut budget), Err(SearchError::Cancelled));
+    assert_eq!(calls.get(), 4);
+
+    let mut budget = Budget::new();
+    budget.deadline(Instant::now());
+    assert_eq!(regex("(a|b)*c").try_find(&text, &mut budget), Err(SearchError::TimedOut));
+
+    let mut budget = Budget::new();
+    budget.timeout(Duration::from_secs(60));
+    assert_eq!(regex("(a|b)*c").try_find(&text, &mut budget), Ok(None));
+}
+
+#[test]
+fn errors_say_why_the_search_stopped() {
+    assert_eq!(SearchError::StepLimit { limit: 5 }.to_string(), "search exceeds the budget of 5 steps");
+    assert_eq!(SearchError::TimedOut.to_string(), "search passed its deadline");
+    assert_eq!(SearchError::Cancelled.to_string(), "search was cancelled");
+}
+
+#[test]
+fn iterators_within_the_budget_agree_with_the_plain_ones() {
+    let text = "a1 bb22 ccc333 d";
+    let regex = regex("(?P<w>[a-z]+)(?P<n>[0-9]*)");
+    let mut budget = Budget::new();
+    budget.max_steps(10_000);
+
+    let found: Vec<&str> = regex.try_find_iter(text, &mut budget).map(|m| m.expect("within the budget").as_str()).collect();
+    assert_eq!(found, regex.find_iter(text).map(|m| m.as_str()).collect::<Vec<&str>>());
+
+    let numbers: Vec<Option<&str>> = regex.try_captures_iter(text, &mut budget)
+        .map(|caps| caps.expect("within the budget").name("n").map(|m| m.as_str()))
+        .collect();
+    assert_eq!(numbers, vec![Some("1"), Some("22"), Some("333"), Some("")]);
+
+    assert_eq!(regex.try_replace_all(text, "$n$w", &mut budget), Ok(regex.replace_all(text, "$n$w")));
+    assert_eq!(regex.try_replacen(text, 2, "<$w>", &mut budget), Ok(regex.replacen(text, 2, "<$w>")));
+    assert_eq!(regex.try_replace(text, "", &mut budget), Ok(regex.replace(text, "")));
+    assert_eq!(regex.try_grep(text, false, &mut budget), Ok(regex.grep(text, false)));
+    assert_eq!(regex.try_grep_not_overlapped(text, true, &mut budget), Ok(regex.grep_not_overlapped(text, true)));
+
+    let pieces: Result<Vec<&str>, SearchError> = regex.try_split(text, &mut budget).collect();
+    assert_eq!(pieces, Ok(regex.split(text).collect::<Vec<&str>>()));
+    let pieces: Result<Vec<&str>, SearchError> = regex.try_splitn(text, 3, &mut budget).collect();
+    assert_eq!(pieces, Ok(regex.splitn(text, 3).collect::<Vec<&str>>()));
+}
+
+#[test]
+fn iterators_end_with_the_error_of_the_budget() {
+    let text = "ab ".repeat(1000);
+    let mut budget = Budget::new();
+    budget.max_steps(100);
+
+    let found: Vec<Result<&str, SearchError>> = regex("[a-z]+").try_find_iter(&text, &mut budget).map(|m| m.map(|m| m.as_str())).collect();
+    assert!(found.len() > 1 && found[..found.len() - 1].iter().all(|m| *m == Ok("ab")));
+    assert_eq!(found.last(), Some(&Err(SearchError::StepLimit { limit: 100 })));
+
+    let mut budget = Budget::new();
+    budget.max_steps(100);
+    let caps: Vec<bool> = regex("([a-z])+").try_captures_iter(&text, &mut budget).map(|caps| caps.is_ok()).collect();
+    assert_eq!(caps.last(), Some(&false));
+    assert_eq!(caps.iter().filter(|ok| !**ok).count(), 1);
+
+    let mut budget = Budget::new();
+    budget.max_steps(100);
+    assert_eq!(regex("[a-z]+").try_replace_all(&text, "x", &mut budget), Err(SearchError::StepLimit { limit: 100 }));
+
+    let mut budget = Budget::new();
+    budget.max_steps(100);
+    assert_eq!(regex("[a-z]+").try_grep_not_overlapped(&text, true, &mut budget), Err(SearchError::StepLimit { limit: 100 }));
+
+    let mut budget = Budget::new();
+    budget.max_steps(100);
+    assert_eq!(regex("[ab ]*c|x").try_grep(&text, true, &mut budget), Err(SearchError::StepLimit { limit: 100 }));
+
+    let mut budget = Budget::new();
+    budget.max_steps(100);
+    let pieces: Vec<Result<&str, SearchError>> = regex(" ").try_split(&text, &mut budget).collect();
+    assert!(pieces.len() > 1 && pieces[..pieces.len() - 1].iter().all(|piece| *piece == Ok("ab")));
+    assert_eq!(pieces.last(), Some(&Err(SearchError::StepLimit { limit: 100 })));
+
+    let mut budget = Budget::new();
+    budget.max_steps(100);
+    let pieces: Vec<Result<&str, SearchError>> = regex(" ").try_splitn(&text, 500, &mut budget).collect();
+    assert!(pieces.len() < 500);
+    assert_eq!(pieces.last(), Some(&Err(SearchError::StepLimit { limit: 100 })));
+}
@@ -0,0 +1,67 @@
+use lexer::ast::Ast;
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::nfa::Nfa;
+
+const PATTERNS: [&str; 14] = [
+    "a", "ab|ac", "(a|b)*abb", "[a-z]+[0-9]*", "if|else|while", "(é|日本)+x?", "a?b?c?",
+    "(ab)*|abc", "a{2,3}", "a{2,}b", "([^a-b]|a)*b", "((a|b)*c)*", "(a*)*b", "(a|ab)(c|bcd)",
+];
+
+fn parse(pattern: &str) -> (Ast, Dfa) {
+    let expression = Expression::from_str(&pattern.to_string());
+    let nfa = Nfa::from_expression(&expression.units);
+
+    (Ast::from_expression(&expression.units), Dfa::from_nfa(&nfa.states, expression.get_chars()))
+}
+
+#[test]
+fn derivative_dfa_agrees_with_the_nfa_pipeline() {
+    for pattern in PATTERNS.iter() {
+        let (ast, dfa) = parse(pattern);
+        let derived = Dfa::from_ast(&ast);
+
+        assert_eq!(derived.symmetric_difference(&dfa).shortest_match(), None, "{}", pattern);
+        assert!(derived.states.len() <= dfa.states.len() * 2, "{} has {} states", pattern, derived.states.len());
+    }
+}
+
+#[test]
+fn derivatives_match_directly() {
+    let texts = ["", "a", "ab", "abb", "aabb", "x9", "if", "ééx", "日本", "aaa", "abcd", "cbc"];
+
+    for pattern in PATTERNS.iter() {
+        let (ast, dfa) = parse(pattern);
+        for text in texts.iter() {
+            assert_eq!(ast.is_match(text), dfa.accepts(text), "{} on {}", pattern, text);
+        }
+    }
+}
+
+#[test]
+fn intersection_and_complement_compile_natively() {
+    let (identifier, identifier_dfa) = parse("[a-z]+");
+    let (keyword, keyword_dfa) = parse("if|else");
+
+    let names = Ast::and(identifier.clone(), Ast::complement(keyword.clone()));
+    assert!(names.is_match("iff"));
+    assert!(!names.is_match("if"));
+    assert!(!names.is_match(""));
+
+    let derived = Dfa::from_ast(&names);
+    let product = identifier_dfa.difference(&keyword_dfa);
+    assert_eq!(derived.symmetric_difference(&product).shortest_match(), None);
+
+    let derived = Dfa::from_ast(&Ast::complement(identifier));
+    assert_eq!(derived.symmetric_difference(&identifier_dfa.complement()).shortest_match(), None);
+    assert!(derived.accepts("") && derived.accepts("A") && !derived.accepts("ab"));
+}
+
+#[test]
+fn normalize_ignores_member_order() {
+    let (lhs, _dfa) = parse("a|b(c|d)");
+    let (rhs, _dfa) = parse("b(d|c)|a");
+
+    assert_ne!(lhs, rhs);
+    assert_eq!(lhs.normalize(), rhs.normalize());
+}
@@ -0,0 +1,52 @@
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::nfa::Nfa;
+
+fn build(pattern: &str) -> Dfa {
+    let expression = Expression::from_str(&pattern.to_string());
+    let nfa = Nfa::from_expression(&expression.units);
+
+    Dfa::from_nfa(&nfa.states, expression.get_chars())
+}
+
+#[test]
+fn strings_come_in_shortlex_order() {
+    let strings: Vec<String> = build("(a|b)c?|ba").iter_strings().collect();
+    assert_eq!(strings, vec!["a", "b", "ac", "ba", "bc"]);
+
+    let strings: Vec<String> = build("a*").iter_strings().take(4).collect();
+    assert_eq!(strings, vec!["", "a", "aa", "aaa"]);
+
+    let strings: Vec<String> = build("(aa)*b").iter_strings().take(3).collect();
+    assert_eq!(strings, vec!["b", "aab", "aaaab"]);
+
+    let strings: Vec<String> = build("[a-c][0-1]").iter_strings().collect();
+    assert_eq!(strings, vec!["a0", "a1", "b0", "b1", "c0", "c1"]);
+
+    assert_eq!(build("a").intersection(&build("b")).iter_strings().next(), None);
+}
+
+#[test]
+fn enumerated_strings_are_accepted_and_counted() {
+    for pattern in ["(a|b)*c", "[a-c]+[0-9]?", "if|in|int", "(ab|a)(b|é)*"] {
+        let dfa = build(pattern);
+        let counts = dfa.count_strings(4);
+
+        let strings: Vec<String> = dfa.iter_strings().take_while(|s| s.chars().count() <= 4).collect();
+        for s in strings.iter() {
+            assert!(dfa.accepts(s), "{} {}", pattern, s);
+        }
+        for (len, &count) in counts.iter().enumerate() {
+            assert_eq!(strings.iter().filter(|s| s.chars().count() == len).count() as u64, count, "{} {}", pattern, len);
+        }
+    }
+}
+
+#[test]
+fn counts_saturate() {
+    let counts = build("[^a-a]*").count_strings(5);
+
+    assert_eq!(counts[0], 1);
+    assert_eq!(counts[1], 0x110000 - 2048 - 1);
+    assert_eq!(counts[5], u64::MAX);
+}
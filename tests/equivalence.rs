@@ -0,0 +1,41 @@
+use lexer::regex::*;
+
+fn regex(pattern: &str) -> Regex {
+    Regex::from(pattern)
+}
+
+#[test]
+fn rewritten_patterns_are_equivalent() {
+    let pairs = [
+        ("a(b|c)", "ab|ac"),
+        ("(a*)*", "a*"),
+        ("a+", "aa*"),
+        ("[a-c]", "a|b|c"),
+        ("a{2,3}", "aaa?"),
+        ("(a|b)*", "(a*b*)*"),
+        ("[^b-c]|[b-c]", "[^a-a]|a"),
+        ("(é|日)?x", "x|éx|日x"),
+    ];
+
+    for (a, b) in pairs.iter() {
+        assert_eq!(regex(a).is_equivalent(&regex(b)), Ok(()), "{} == {}", a, b);
+        assert_eq!(regex(a).is_subset_of(&regex(b)), Ok(()), "{} <= {}", a, b);
+        assert_eq!(regex(b).is_subset_of(&regex(a)), Ok(()), "{} <= {}", b, a);
+    }
+}
+
+#[test]
+fn differing_patterns_give_the_shortest_counterexample() {
+    assert_eq!(regex("a*").is_equivalent(&regex("a+")), Err(String::from("")));
+    assert_eq!(regex("ab|ac").is_equivalent(&regex("a[bc]d?")), Err(String::from("abd")));
+    assert_eq!(regex("(ab)*").is_equivalent(&regex("(ab)*|abab(ab)*c")), Err(String::from("ababc")));
+    assert_eq!(regex("[a-z]+").is_equivalent(&regex("[a-y]+")), Err(String::from("z")));
+}
+
+#[test]
+fn subset_checks_one_direction() {
+    assert_eq!(regex("ab").is_subset_of(&regex("a[a-z]")), Ok(()));
+    assert_eq!(regex("a[a-z]").is_subset_of(&regex("ab")), Err(String::from("aa")));
+    assert_eq!(regex("if|else").is_subset_of(&regex("[a-z]+")), Ok(()));
+    assert_eq!(regex("x1|if").is_subset_of(&regex("[a-z]+")), Err(String::from("x1")));
+}
@@ -0,0 +1,91 @@
+use lexer::ast::Ast;
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::regex::*;
+
+fn build(pattern: &str) -> Regex {
+    RegexBuilder::new(pattern).build()
+}
+
+#[test]
+fn complement_excludes_a_substring() {
+    // `(a|~a)` is any string, as nothing is outside both
+    let regex = build("/[*]~((a|~a)[*]/(a|~a))[*]/");
+
+    assert_eq!(regex.find("x /* a * b */ y */").map(|m| m.as_str()), Some("/* a * b */"));
+    assert!(regex.dfa().accepts("/**/"));
+    assert!(regex.dfa().accepts("/* / * */"));
+    assert!(!regex.dfa().accepts("/* */ */"));
+}
+
+#[test]
+fn intersection_removes_keywords() {
+    let regex = build("[a-z]+&~(if|else)");
+    let dfa = regex.dfa();
+
+    assert!(dfa.accepts("iff") && dfa.accepts("i") && dfa.accepts("elsewhere"));
+    assert!(!dfa.accepts("if") && !dfa.accepts("else") && !dfa.accepts(""));
+}
+
+#[test]
+fn operators_bind_in_order() {
+    let same = |lhs: &str, rhs: &str| assert_eq!(build(lhs).is_equivalent(&build(rhs)), Ok(()), "{} {}", lhs, rhs);
+
+    same("~a*", "~(a*)");
+    same("~ab", "(~a)b");
+    same("ab&a[a-z]", "ab");
+    same("a|b&c", "a");
+    same("a*&~a*", "a&~a");
+    same("~~(ab)", "ab");
+}
+
+#[test]
+fn extended_patterns_print_back() {
+    for pattern in ["~a*", "(~a)*", "a~b", "~(ab)", "a&b|c", "(a|b)&c", "a(b&c)", "[&~]x&~x"].iter() {
+        let expression = Expression::from_str(&pattern.to_string());
+        assert_eq!(expression.to_string(), *pattern);
+    }
+}
+
+#[test]
+fn derivatives_agree_with_the_product() {
+    for pattern in ["[a-z]+&~(if|else)", "~(a|b)*c", "(ab|a)*&~(a*)", "~[0-9]&[0-9a-c]"].iter() {
+        let regex = build(pattern);
+        let expression = Expression::from_str(&pattern.to_string());
+        let derived = Dfa::from_ast(&Ast::from_expression(&expression.units));
+
+        assert_eq!(derived.symmetric_difference(regex.dfa()).shortest_match(), None, "{}", pattern);
+    }
+}
+
+#[test]
+fn escaped_operators_are_literal_chars() {
+    let regex = build("a\\&b");
+    assert!(regex.dfa().accepts("a&b"));
+    assert!(!regex.dfa().accepts("ab"));
+
+    let regex = build("\\~a|x\\*");
+    assert!(regex.dfa().accepts("~a") && regex.dfa().accepts("x*"));
+    assert!(!regex.dfa().accepts("b") && !regex.dfa().accepts("xx"));
+
+    // the escape covers one char only
+    let regex = build("\\&a\\&&[&a]+");
+    assert!(regex.dfa().accepts("&a&"));
+    assert_eq!(build("\\(a\\)").find("f(a)").map(|m| m.as_str()), Some("(a)"));
+}
+
+#[test]
+fn escaped_chars_print_back_as_sets() {
+    let expression = Expression::from_str(&String::from("a\\&b\\~"));
+    assert_eq!(expression.to_string(), "a[&]b[~]");
+}
+
+#[test]
+fn empty_string_and_empty_language_have_stand_ins() {
+    let everything = Ast::complement(Ast::Nothing).to_expression().expect("not the empty language");
+    assert_eq!(everything.to_string(), "~(a&~a)");
+    assert!(build(&everything.to_string()).dfa().accepts("any string"));
+
+    assert!(Ast::Nothing.to_expression().is_none());
+    assert_eq!(Ast::Empty.to_expression().expect("the empty string").to_string(), "a{0}");
+}
@@ -0,0 +1,72 @@
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::fastate::*;
+use lexer::glushkov::Glushkov;
+use lexer::nfa::Nfa;
+use lexer::regex::*;
+
+fn glushkov(pattern: &str) -> Glushkov {
+    let expression = Expression::from_str(&pattern.to_string());
+    Glushkov::from_expression(&expression.units).expect("Failed to get glushkov")
+}
+
+fn thompson_dfa(pattern: &str) -> Dfa {
+    let expression = Expression::from_str(&pattern.to_string());
+    let nfa = Nfa::from_expression(&expression.units);
+    Dfa::from_nfa(&nfa.states, expression.get_chars())
+}
+
+#[test]
+fn one_state_per_position_and_no_epsilon() {
+    let automaton = glushkov("(a|b)*abb");
+    let states = automaton.states();
+
+    assert_eq!(states.len(), 6);
+    assert!(states.iter().all(|state| state.trans.iter().all(|tran| matches!(tran, Tran::Char(_, _)))));
+    assert_eq!(automaton.accepting, vec![false, false, false, false, false, true]);
+
+    let automaton = glushkov("a{2,3}b?");
+    assert_eq!(automaton.states().len(), 5);
+    assert_eq!(automaton.accepting, vec![false, false, true, true, true]);
+}
+
+#[test]
+fn subset_construction_agrees_with_thompson() {
+    let patterns = ["a", "(a|b)*abb", "[a-z]+[0-9]*", "if|else|while", "(é|日本)+x?", "a?b?c?", "(ab)*|abc", "a{2,3}", "a{2,}b", "((a|b)*c)*"];
+
+    for pattern in patterns.iter() {
+        let dfa = Dfa::from_glushkov(&glushkov(pattern));
+        assert_eq!(dfa.symmetric_difference(&thompson_dfa(pattern)).shortest_match(), None, "{}", pattern);
+    }
+
+    let dfa = Dfa::from_glushkov(&glushkov("a{0,2}"));
+    assert!(dfa.accepts("") && dfa.accepts("a") && dfa.accepts("aa") && !dfa.accepts("aaa"));
+}
+
+#[test]
+fn searches_agree_with_thompson() {
+    let patterns = ["(a|ab)(c|bcd)", "a*|b", "(a|b{0})*c", "[a-c]+?", "(ab|a)*b", "x*"];
+    let texts = ["abcd", "aabbcab", "bca", "", "ééaxb"];
+
+    for &kind in [MatchKind::LeftmostFirst, MatchKind::LeftmostLongest, MatchKind::Earliest].iter() {
+        for pattern in patterns.iter() {
+            let thompson = RegexBuilder::new(pattern).match_kind(kind).build();
+            let glushkov = RegexBuilder::new(pattern).match_kind(kind).nfa_kind(NfaKind::Glushkov).build();
+
+            for text in texts.iter() {
+                let lhs: Vec<(usize, usize)> = thompson.find_iter(text).map(|m| (m.start(), m.end())).collect();
+                let rhs: Vec<(usize, usize)> = glushkov.find_iter(text).map(|m| (m.start(), m.end())).collect();
+                assert_eq!(lhs, rhs, "{:?} {} on {}", kind, pattern, text);
+            }
+        }
+    }
+}
+
+#[test]
+fn extended_patterns_fall_back_to_thompson() {
+    let expression = Expression::from_str(&String::from("[a-z]+&~(if)"));
+    assert!(Glushkov::from_expression(&expression.units).is_none());
+
+    let regex = RegexBuilder::new("[a-z]+&~(if)").nfa_kind(NfaKind::Glushkov).build();
+    assert!(regex.dfa().accepts("iff") && !regex.dfa().accepts("if"));
+}
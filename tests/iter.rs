@@ -0,0 +1,77 @@
+use lexer::dfa::MatchKind;
+use lexer::matches::Captures;
+use lexer::regex::*;
+
+fn build(pattern: &str) -> Regex {
+    RegexBuilder::new(pattern).match_kind(MatchKind::LeftmostFirst).build()
+}
+
+#[test]
+fn find_iter_returns_non_overlapping_matches() {
+    let regex = build("[0-9]+");
+    let found: Vec<&str> = regex.find_iter("a1 b22 c333").map(|m| m.as_str()).collect();
+
+    assert_eq!(found, vec!["1", "22", "333"]);
+}
+
+#[test]
+fn find_iter_advances_past_empty_matches() {
+    let regex = build("a*");
+    let found: Vec<(usize, usize)> = regex.find_iter("baab").map(|m| (m.start(), m.end())).collect();
+
+    assert_eq!(found, vec![(0, 0), (1, 3), (4, 4)]);
+
+    let regex = build("x*");
+    let found: Vec<(usize, usize)> = regex.find_iter("é").map(|m| (m.start(), m.end())).collect();
+
+    assert_eq!(found, vec![(0, 0), (2, 2)]);
+}
+
+#[test]
+fn captures_reports_numbered_and_named_groups() {
+    let regex = build("(?P<key>[a-z]+)=([0-9]+)?");
+    let caps = regex.captures("x: foo=42").unwrap();
+
+    assert_eq!(caps.len(), 3);
+    assert_eq!(caps.get(0).unwrap().as_str(), "foo=42");
+    assert_eq!(caps.name("key").unwrap().as_str(), "foo");
+    assert_eq!(caps.get(2).unwrap().as_str(), "42");
+
+    let caps = regex.captures("bar=").unwrap();
+    assert_eq!(caps.get(2), None);
+}
+
+#[test]
+fn captures_iter_keeps_the_last_repetition() {
+    let regex = build("(a|b)+");
+    let groups: Vec<&str> = regex.captures_iter("ab ba").map(|caps| caps.get(1).unwrap().as_str()).collect();
+
+    assert_eq!(groups, vec!["b", "a"]);
+}
+
+#[test]
+fn split_and_splitn() {
+    let regex = build(", *");
+
+    assert_eq!(regex.split("a, b,c").collect::<Vec<&str>>(), vec!["a", "b", "c"]);
+    assert_eq!(regex.split(",a,").collect::<Vec<&str>>(), vec!["", "a", ""]);
+    assert_eq!(regex.splitn("a, b,c", 2).collect::<Vec<&str>>(), vec!["a", "b,c"]);
+    assert_eq!(regex.splitn("a, b,c", 0).count(), 0);
+}
+
+#[test]
+fn replace_expands_group_references() {
+    let regex = build("(?<y>[0-9]{4})-([0-9]{2})");
+
+    assert_eq!(regex.replace("2024-05 1999-12", "$2/${y}"), "05/2024 1999-12");
+    assert_eq!(regex.replace_all("2024-05 1999-12", "$2/$y"), "05/2024 12/1999");
+    assert_eq!(regex.replace_all("2024-05", "$$1 ${9}"), "$1 ");
+}
+
+#[test]
+fn replace_all_with_a_closure() {
+    let regex = build("[a-z]+");
+    let res = regex.replace_all("ab cd", |caps: &Captures| caps.get(0).unwrap().as_str().to_uppercase());
+
+    assert_eq!(res, "AB CD");
+}
@@ -0,0 +1,119 @@
+use lexer::regex::*;
+use std::time::Instant;
+
+fn build(pattern: &str, size_limit: usize, dfa_size_limit: usize) -> Result<Regex, BuildError> {
+    RegexBuilder::new(pattern).size_limit(size_limit).dfa_size_limit(dfa_size_limit).try_build()
+}
+
+#[test]
+fn patterns_under_the_limits_build() {
+    let regex = build("[a-z]+(_[0-9]{2,4})?", 1000, 1000).expect("a small pattern");
+    assert_eq!(regex.find("let foo_123 = 1").map(|m| m.as_str()), Some("let"));
+
+    let regex = build("(a|b)*a(a|b){3}", 100, 100).expect("sixteen dfa states");
+    assert_eq!(regex.find("abbab").map(|m| m.as_str()), Some("abba"));
+}
+
+#[test]
+fn nested_repeats_exceed_the_nfa_limit() {
+    // a billion copies of `a`, refused before any of them is made
+    let error = build("((a{1000}){1000}){1000}", 100_000, usize::MAX).err();
+    assert_eq!(error, Some(BuildError::NfaTooBig { limit: 100_000 }));
+
+    let error = build("(x{50}y{50}){20}", 1000, usize::MAX).err();
+    assert_eq!(error, Some(BuildError::NfaTooBig { limit: 1000 }));
+    assert!(build("(x{50}y{50}){2}", 1000, usize::MAX).is_ok());
+}
+
+#[test]
+fn exponential_dfas_exceed_the_dfa_limit() {
+    // the n-th char from the end needs 2^n dfa states
+    let error = build("(a|b)*a(a|b){24}", usize::MAX, 1000).err();
+    assert_eq!(error, Some(BuildError::DfaTooBig { limit: 1000 }));
+
+    let mut builder = RegexBuilder::new("(a|b)*a(a|b){24}");
+    builder.nfa_kind(NfaKind::Glushkov).dfa_size_limit(1000);
+    assert_eq!(builder.try_build().err(), Some(BuildError::DfaTooBig { limit: 1000 }));
+}
+
+#[test]
+fn intersections_and_complements_are_held_to_the_dfa_limit() {
+    let error = build("~((a|b)*a(a|b){24})", usize::MAX, 1000).err();
+    assert_eq!(error, Some(BuildError::DfaTooBig { limit: 1000 }));
+
+    let error = build("(a|b)*a(a|b){6}&(a|b)*b(a|b){6}", usize::MAX, 1000).err();
+    assert_eq!(error, Some(BuildError::DfaTooBig { limit: 1000 }));
+}
+
+#[test]
+fn errors_name_the_limit() {
+    assert_eq!(BuildError::NfaTooBig { limit: 10 }.to_string(), "nfa exceeds the size limit of 10 states");
+    assert_eq!(BuildError::DfaTooBig { limit: 20 }.to_string(), "dfa exceeds the size limit of 20 states");
+}
+
+#[test]
+fn alternated_classes_build_quickly_under_the_limits() {
+    let start = Instant::now();
+    let regex = build("([a-z]|[0-9]|_){1,10}x", 2000, 200).expect("41 dfa states");
+
+    assert!(start.elapsed().as_secs() < 2, "{:?}", start.elapsed());
+    assert_eq!(regex.find("--ab_9x--").map(|m| m.as_str()), Some("ab_9x"));
+}
+
+#[test]
+fn repeats_from_zero_build() {
+    let regex = build("a{0,3}b", 10_000, 10_000).expect("a bounded repeat from zero");
+    assert_eq!(regex.find("xaaaab").map(|m| m.as_str()), Some("aaab"));
+    assert_eq!(regex.find("xb").map(|m| m.as_str()), Some("b"));
+    assert!(regex.dfa().accepts("b") && !regex.dfa().accepts("aaaab"));
+
+    let regex = build("a{0,}b", 10_000, 10_000).expect("an unbounded repeat from zero");
+    assert!(regex.dfa().accepts("b") && regex.dfa().accepts("aaaab"));
+
+    let regex = build("xa{0,0}", 10_000, 10_000).expect("a repeat of nothing");
+    assert!(regex.dfa().accepts("x") && !regex.dfa().accepts("xa"));
+}
+
+#[test]
+fn malformed_patterns_are_syntax_errors() {
+    let cases = [
+        ("a{0,3}", None),
+        ("a{0,}", None),
+        ("()", Some((0, "empty group"))),
+        ("a||b", Some((2, "missing operand"))),
+        ("(a", Some((0, "unclosed ("))),
+        ("[", Some((0, "unclosed set"))),
+        ("a)", Some((1, "unmatched )"))),
+        ("ab]", Some((2, "unmatched ]"))),
+        ("*a", Some((0, "repeat without an operand"))),
+        ("a{3,1}", Some((1, "invalid repeat"))),
+        ("a{2", Some((1, "unclosed repeat"))),
+        ("a\\", Some((1, "trailing backslash"))),
+        ("a|", Some((2, "missing operand"))),
+        ("", Some((0, "missing operand"))),
+    ];
+
+    for (pattern, error) in cases.iter() {
+        let expected = error.map(|(at, message)| BuildError::Syntax { at, message });
+        assert_eq!(build(pattern, 10_000, 10_000).err(), expected, "{}", pattern);
+    }
+
+    let error = BuildError::Syntax { at: 3, message: "unclosed (" };
+    assert_eq!(error.to_string(), "syntax error at 3: unclosed (");
+}
+
+#[test]
+fn glushkov_follow_lists_are_held_to_the_nfa_limit() {
+    let glushkov = |pattern: &str, size_limit: usize| {
+        RegexBuilder::new(pattern).nfa_kind(NfaKind::Glushkov).size_limit(size_limit).dfa_size_limit(usize::MAX).try_build()
+    };
+
+    // a hundred positions, each followed by all of them
+    let pattern = format!("({})*", (0..100).map(|i| format!("x{}", i)).collect::<Vec<_>>().join("|"));
+    assert!(build(&pattern, 1000, usize::MAX).is_ok());
+    assert_eq!(glushkov(&pattern, 1000).err(), Some(BuildError::NfaTooBig { limit: 1000 }));
+
+    let error = glushkov("((a{1000}){1000}){1000}", 100_000).err();
+    assert_eq!(error, Some(BuildError::NfaTooBig { limit: 100_000 }));
+    assert!(glushkov("(a|b)*a(a|b){3}", 100).is_ok());
+}
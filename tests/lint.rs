@@ -0,0 +1,88 @@
+use lexer::expression::*;
+use lexer::lint::*;
+
+fn kinds(pattern: &str) -> Vec<(LintKind, &str)> {
+    lint(pattern).into_iter()
+        .map(|warning| (warning.kind, &pattern[warning.span.0..warning.span.1]))
+        .collect()
+}
+
+#[test]
+fn clean_patterns_have_no_warnings() {
+    for pattern in ["ab|a", "[a-z][0-9]*", "(?P<n>a|ab)c", "(a{10}b{20}){30}", "x(a|b)+y"].iter() {
+        assert_eq!(lint(pattern), vec![], "{}", pattern);
+    }
+}
+
+#[test]
+fn empty_matches() {
+    assert_eq!(kinds("x?"), vec![(LintKind::EmptyMatch, "x?")]);
+    assert_eq!(kinds("(a|b*)"), vec![(LintKind::EmptyMatch, "(a|b*)")]);
+}
+
+#[test]
+fn nested_quantifiers() {
+    assert_eq!(kinds("(a*)*b"), vec![(LintKind::NestedQuantifier, "(a*)*")]);
+    assert_eq!(kinds("c(a+){3}"), vec![(LintKind::NestedQuantifier, "(a+){3}")]);
+    assert_eq!(kinds("c(a+)?"), vec![]);
+}
+
+#[test]
+fn redundant_and_unreachable_branches() {
+    assert_eq!(kinds("x([a-c]|b)"), vec![(LintKind::RedundantBranch, "b")]);
+    assert_eq!(kinds("x(b|[a-c])"), vec![(LintKind::RedundantBranch, "b")]);
+    assert_eq!(kinds("a|ab|b|a"), vec![
+        (LintKind::UnreachableBranch, "ab"),
+        (LintKind::RedundantBranch, "a"),
+    ]);
+    assert_eq!(kinds("a*|b"), vec![
+        (LintKind::EmptyMatch, "a*|b"),
+        (LintKind::UnreachableBranch, "b"),
+    ]);
+}
+
+#[test]
+fn overlapping_ranges() {
+    assert_eq!(kinds("[abca]"), vec![(LintKind::OverlappingRanges, "[abca]")]);
+    // only one range is read per class, this is the set of `a-z09`
+    assert_eq!(lint("[a-z0-9]")[0].message, "`-` is listed more than once in the class");
+    let warnings = lint("x([a-m]|[k-z])");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].message, "the class overlaps `[a-m]` on `k`-`m`");
+}
+
+#[test]
+fn huge_repeats() {
+    assert_eq!(kinds("(a{40}){50}"), vec![(LintKind::HugeRepeat, "(a{40}){50}")]);
+    assert_eq!(kinds("b|a{2000}"), vec![(LintKind::HugeRepeat, "a{2000}")]);
+}
+
+#[test]
+fn malformed_patterns_get_a_syntax_warning() {
+    assert_eq!(kinds("(a"), vec![(LintKind::Syntax, "(")]);
+    assert_eq!(kinds("é||b"), vec![(LintKind::Syntax, "|")]);
+    assert_eq!(kinds("a{2,1}"), vec![(LintKind::Syntax, "{")]);
+
+    let warnings = lint("ab[");
+    assert_eq!(warnings[0].render("ab["), "warning[syntax]: syntax error: unclosed set\n  ab[\n    ^");
+}
+
+#[test]
+fn warnings_render_with_a_caret_under_the_span() {
+    let warnings = lint("é(a*)*");
+    assert_eq!(warnings[0].render("é(a*)*"), concat!(
+        "warning[nested-quantifier]: nested quantifier, the repeated operand matches the empty string: `a*`\n",
+        "  é(a*)*\n",
+        "   ^^^^^",
+    ));
+}
+
+#[test]
+fn spans_point_into_the_pattern() {
+    let pattern = "(?P<x>ab)|[c-d]{2}";
+    let (expression, spans) = Expression::from_str_with_spans(pattern);
+    let texts: Vec<&str> = spans.iter().map(|&(start, end)| &pattern[start..end]).collect();
+
+    assert_eq!(expression.units.len(), texts.len());
+    assert_eq!(texts, vec!["a", "b", "", "(?P<x>ab)", "[c-d]", "{2}", "|"]);
+}
@@ -0,0 +1,69 @@
+use lexer::expression::*;
+use lexer::literal::*;
+use lexer::regex::*;
+
+fn literals(pattern: &str) -> Literals {
+    Literals::from_expression(&Expression::from_str(&pattern.to_string()).units)
+}
+
+fn strings(strings: &[&str]) -> Vec<String> {
+    strings.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn prefixes_cross_alternations() {
+    assert_eq!(literals("foo|bar").prefixes, strings(&["foo", "bar"]));
+    assert_eq!(literals("(ab|cd)x").prefixes, strings(&["abx", "cdx"]));
+    assert_eq!(literals("[ab]c").prefixes, strings(&["ac", "bc"]));
+    assert_eq!(literals("(foo)?bar").prefixes, strings(&["foobar", "bar"]));
+    assert_eq!(literals("ab(c|d)*e").prefixes, strings(&["ab"]));
+}
+
+#[test]
+fn no_prefilter_when_a_match_may_start_anywhere() {
+    // a repeat that can be skipped leaves no finite set of prefixes, and
+    // a pattern matching the empty string has the empty prefix
+    for pattern in ["a*b", "(a|b*)c", "x*", "a?", "[a-z]+foo[0-9]"].iter() {
+        let literals = literals(pattern);
+        assert_eq!(literals.prefixes, Vec::<String>::new(), "{}", pattern);
+        assert!(Prefilter::new(&literals.prefixes).is_none(), "{}", pattern);
+    }
+
+    let regex = Regex::from(&String::from("a*b"));
+    assert_eq!(regex.grep("xxb", true), Some((2, 3)));
+    assert_eq!(regex.grep("xaab", true), Some((1, 4)));
+}
+
+#[test]
+fn required_literals() {
+    assert_eq!(literals("[a-z]+foo[0-9]").required, Some(String::from("foo")));
+    assert_eq!(literals("(ab|cd)x").required, Some(String::from("x")));
+    assert_eq!(literals("foo|bar").required, None);
+    assert_eq!(literals("x*").required, None);
+
+    let regex = Regex::from(&String::from("[0-9]+foo[0-9]"));
+    assert_eq!(regex.grep("12fo3 45foo", true), None);
+    assert_eq!(regex.grep_all("12fo3 45foo", true), vec![]);
+    assert_eq!(regex.grep("12fo3 45foo6", true), Some((6, 12)));
+}
+
+#[test]
+fn prefilters_by_prefix_count() {
+    assert!(matches!(Prefilter::new(&strings(&["x"])), Some(Prefilter::Byte(b'x'))));
+    assert!(matches!(Prefilter::new(&strings(&["foo"])), Some(Prefilter::Literal(_))));
+
+    let prefilter = Prefilter::new(&strings(&["foo", "bar"])).expect("two prefixes");
+    assert!(matches!(prefilter, Prefilter::Multiple(_)));
+    assert_eq!(prefilter.find("xxbarfoo", 0), Some(2));
+    assert_eq!(prefilter.find("xxbarfoo", 3), Some(5));
+    assert_eq!(prefilter.find("xxbarfoo", 6), None);
+}
+
+#[test]
+fn multiple_prefixes_find_every_match() {
+    let regex = Regex::from(&String::from("(foo|bar)[0-9]"));
+    let content = "foo bar1 baz2 foo3 fo4";
+
+    assert_eq!(regex.grep(content, true), Some((4, 8)));
+    assert_eq!(regex.grep_all(content, true), vec![(4, 8), (14, 18)]);
+}
@@ -0,0 +1,54 @@
+use lexer::dfa::MatchKind;
+use lexer::regex::*;
+
+fn build(pattern: &str, kind: MatchKind) -> Regex {
+    RegexBuilder::new(pattern).match_kind(kind).build()
+}
+
+#[test]
+fn leftmost_longest_is_the_default() {
+    let regex = Regex::from(&String::from("a|ab"));
+
+    assert_eq!(regex.match_kind(), MatchKind::LeftmostLongest);
+    assert_eq!(regex.grep("xab", true), Some((1, 3)));
+}
+
+#[test]
+fn leftmost_first_prefers_earlier_alternatives() {
+    let regex = build("a|ab", MatchKind::LeftmostFirst);
+    assert_eq!(regex.grep("xab", true), Some((1, 2)));
+    assert_eq!(regex.matcher("ab", true), Some(0));
+
+    let regex = build("ab|a", MatchKind::LeftmostFirst);
+    assert_eq!(regex.grep("xab", true), Some((1, 3)));
+}
+
+#[test]
+fn leftmost_first_repeats_are_greedy() {
+    let regex = build("(a|ab)(c|bcd)", MatchKind::LeftmostFirst);
+    assert_eq!(regex.grep("abcd", true), Some((0, 4)));
+
+    let regex = build("a*(ab)?", MatchKind::LeftmostFirst);
+    assert_eq!(regex.grep("aab", true), Some((0, 2)));
+
+    let regex = build("a*(ab)?", MatchKind::LeftmostLongest);
+    assert_eq!(regex.grep("aab", true), Some((0, 3)));
+}
+
+#[test]
+fn leftmost_start_wins_over_earlier_end() {
+    for kind in [MatchKind::LeftmostFirst, MatchKind::LeftmostLongest].iter() {
+        let regex = build("abcd|bc", *kind);
+        assert_eq!(regex.grep("abcd", true), Some((0, 4)));
+    }
+}
+
+#[test]
+fn earliest_stops_at_the_first_end() {
+    let regex = build("abcd|bc", MatchKind::Earliest);
+    assert_eq!(regex.grep("abcd", true), Some((1, 3)));
+
+    let regex = build("a+", MatchKind::Earliest);
+    assert_eq!(regex.grep("baaa", true), Some((1, 2)));
+    assert_eq!(regex.matcher("aaa", true), Some(0));
+}
@@ -0,0 +1,86 @@
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::nfa::Nfa;
+
+fn build(pattern: &str) -> Dfa {
+    let expression = Expression::from_str(&pattern.to_string());
+    let nfa = Nfa::from_expression(&expression.units);
+
+    Dfa::from_nfa(&nfa.states, expression.get_chars())
+}
+
+// Every string of length up to 4 over "abcé".
+fn strings() -> Vec<String> {
+    let mut all = vec![String::new()];
+    let mut last = vec![String::new()];
+    for _ in 0..4 {
+        last = last.iter().flat_map(|s| "abcé".chars().map(move |ch| format!("{}{}", s, ch))).collect();
+        all.extend(last.iter().cloned());
+    }
+
+    all
+}
+
+#[test]
+fn identifiers_that_are_not_keywords() {
+    let identifier = build("[a-z]+");
+    let keyword = build("if|else|while");
+    let names = identifier.difference(&keyword);
+
+    assert!(names.accepts("iff"));
+    assert!(names.accepts("i"));
+    assert!(names.accepts("whil"));
+    assert!(!names.accepts("if"));
+    assert!(!names.accepts("while"));
+    assert!(!names.accepts(""));
+    assert!(!names.accepts("x1"));
+    assert!(names.minimize().to_string().starts_with("digraph dfa {"));
+}
+
+#[test]
+fn boolean_operations_agree_with_the_operands() {
+    let patterns = ["a(b|c)*", "[ab]*c?", "(a|é)+b", "c|abc|a*", "[^a]+"];
+
+    for p in patterns.iter() {
+        let a = build(p);
+        let complement = a.complement();
+        let minimal = a.minimize();
+
+        for q in patterns.iter() {
+            let b = build(q);
+            let (and, or, minus) = (a.intersection(&b), a.union(&b), a.difference(&b));
+
+            for s in strings() {
+                let (x, y) = (a.accepts(&s), b.accepts(&s));
+                assert_eq!(and.accepts(&s), x && y, "{} & {} on {}", p, q, s);
+                assert_eq!(or.accepts(&s), x || y, "{} | {} on {}", p, q, s);
+                assert_eq!(minus.accepts(&s), x && !y, "{} - {} on {}", p, q, s);
+            }
+        }
+
+        for s in strings().iter().chain(["z", "azz", "\u{10FFFF}"].iter().map(|s| s.to_string()).collect::<Vec<String>>().iter()) {
+            assert_eq!(complement.accepts(s), !a.accepts(s), "~{} on {}", p, s);
+            assert_eq!(minimal.accepts(s), a.accepts(s), "min {} on {}", p, s);
+        }
+    }
+}
+
+#[test]
+fn minimize_finds_the_smallest_dfa() {
+    assert_eq!(build("(a|b)*abb").minimize().states.len(), 4);
+    assert_eq!(build("a*|a*a").minimize().states.len(), 1);
+    assert_eq!(build("(ab|ab)(c|c)").minimize().states.len(), 4);
+
+    let empty = build("a").intersection(&build("b")).minimize();
+    assert_eq!(empty.states.len(), 1);
+    assert!(empty.tail.is_empty());
+}
+
+#[test]
+fn complement_is_total() {
+    let dfa = build("ab").complement();
+    for s in ["", "a", "abc", "zzz", "日本"] {
+        let state = s.chars().fold(dfa.start_state(), |state, ch| dfa.next_state(state, ch));
+        assert!(!dfa.is_dead_state(state));
+    }
+}
@@ -0,0 +1,90 @@
+use lexer::dfa::*;
+use lexer::expression::*;
+use lexer::nfa::*;
+
+const PATTERNS: [&str; 8] = [
+    "abc",
+    "a|bc",
+    "(ab)*c",
+    "a+b?",
+    "[a-c]{2}",
+    "a{1,2}b",
+    "(a|b)*abb",
+    "c(a|[ab])*",
+];
+
+fn strings(max_len: usize) -> Vec<String> {
+    let mut res: Vec<String> = vec![String::new()];
+    let mut last: Vec<String> = vec![String::new()];
+
+    for _len in 0..max_len {
+        let mut next: Vec<String> = Vec::new();
+        for s in last.iter() {
+            for ch in ['a', 'b', 'c'].iter() {
+                next.push(format!("{}{}", s, ch));
+            }
+        }
+        res.extend(next.iter().cloned());
+        last = next;
+    }
+
+    res
+}
+
+fn reversed(s: &str) -> String {
+    s.chars().rev().collect()
+}
+
+fn build(pattern: &str) -> (Nfa, Dfa) {
+    let expression = Expression::from_str(&pattern.to_string());
+    let nfa = Nfa::from_expression(&expression.units);
+    let dfa = Dfa::from_nfa(&nfa.states, expression.get_chars());
+
+    (nfa, dfa)
+}
+
+#[test]
+fn reversed_nfa_accepts_reversed_language() {
+    for pattern in PATTERNS.iter() {
+        let (nfa, dfa) = build(pattern);
+        let reverse = Dfa::from_nfa(&nfa.reverse().states, dfa.get_chars());
+
+        for s in strings(6) {
+            assert_eq!(dfa.accepts(&s), reverse.accepts(&reversed(&s)), "{} on {:?}", pattern, s);
+        }
+    }
+}
+
+#[test]
+fn reversed_dfa_accepts_reversed_language() {
+    for pattern in PATTERNS.iter() {
+        let (_nfa, dfa) = build(pattern);
+        let reverse = dfa.reverse();
+
+        for s in strings(6) {
+            assert_eq!(dfa.accepts(&s), reverse.accepts(&reversed(&s)), "{} on {:?}", pattern, s);
+        }
+    }
+}
+
+#[test]
+fn reversing_twice_gives_the_same_language() {
+    for pattern in PATTERNS.iter() {
+        let (_nfa, dfa) = build(pattern);
+        let twice = dfa.reverse().reverse();
+
+        for s in strings(6) {
+            assert_eq!(dfa.accepts(&s), twice.accepts(&s), "{} on {:?}", pattern, s);
+        }
+    }
+}
+
+#[test]
+fn reversed_dfa_accepts_empty_string_when_head_accepts() {
+    let (_nfa, dfa) = build("(ab)*");
+    let reverse = dfa.reverse();
+
+    assert!(reverse.accepts(""));
+    assert!(reverse.accepts("baba"));
+    assert!(!reverse.accepts("ab"));
+}
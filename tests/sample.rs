@@ -0,0 +1,53 @@
+use lexer::regex::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+
+fn regex(pattern: &str) -> Regex {
+    Regex::from(pattern)
+}
+
+#[test]
+fn samples_match_the_pattern() {
+    let mut rng = StdRng::seed_from_u64(7);
+
+    for pattern in ["[a-z]+[0-9]*", "if|else", "(é|日本)+x?", "0x[0-9a-f]+", "[^a-a]b"] {
+        let regex = regex(pattern);
+        for _ in 0..200 {
+            let s = regex.sample(&mut rng, 8).expect("pattern has short strings");
+            assert!(s.chars().count() <= 8, "{} {}", pattern, s);
+            assert!(regex.dfa().accepts(&s), "{} {}", pattern, s);
+
+            if let Some(s) = regex.sample_uniform(&mut rng, 4) {
+                assert_eq!(s.chars().count(), 4);
+                assert!(regex.dfa().accepts(&s), "{} {}", pattern, s);
+            }
+        }
+    }
+}
+
+#[test]
+fn no_sample_when_nothing_is_short_enough() {
+    let mut rng = StdRng::seed_from_u64(7);
+
+    assert_eq!(regex("abcd").sample(&mut rng, 3), None);
+    assert_eq!(regex("abcd").sample_uniform(&mut rng, 3), None);
+    assert_eq!(regex("a*").sample(&mut rng, 0), Some(String::new()));
+}
+
+#[test]
+fn uniform_samples_are_spread_evenly() {
+    let mut rng = StdRng::seed_from_u64(7);
+    // a plain walk would pick "ab" half of the time
+    let regex = regex("ab|c[a-c]");
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for _ in 0..4000 {
+        *seen.entry(regex.sample_uniform(&mut rng, 2).unwrap()).or_default() += 1;
+    }
+
+    assert_eq!(seen.len(), 4);
+    for (s, &count) in seen.iter() {
+        assert!(800 < count && count < 1200, "{} {}", s, count);
+    }
+}
@@ -0,0 +1,94 @@
+use lexer::dfa::*;
+use lexer::expression::*;
+use lexer::fastate::*;
+use lexer::nfa::*;
+use lexer::regex::*;
+use std::time::Instant;
+
+fn regex(pattern: &str) -> Regex {
+    Regex::from(pattern)
+}
+
+fn dfa(pattern: &str) -> Dfa {
+    let expression = Expression::from_str(&pattern.to_string());
+    Dfa::from_nfa(&Nfa::from_expression(&expression.units).states, expression.get_chars())
+}
+
+// Whether the anchored dfa accepts all of a non-empty content.
+fn accepts(regex: &Regex, content: &str) -> bool {
+    regex.matcher(content, true) == Some(content.chars().count() - 1)
+}
+
+// The matches `grep_all` used to give by running the anchored matcher from
+// every offset of an ascii text.
+fn grep_all_per_offset(regex: &Regex, content: &str) -> Vec<(usize, usize)> {
+    (0..content.len())
+        .filter_map(|i| regex.matcher(&content[i..], true).map(|end| (i, i + end + 1)))
+        .collect()
+}
+
+#[test]
+fn classes_are_built_quickly() {
+    // every state is expanded once, however many trans reach it
+    let start = Instant::now();
+    let dfa = dfa("([a-z]|[0-9]|_){1,10}x");
+
+    assert!(start.elapsed().as_secs() < 2, "{:?}", start.elapsed());
+    assert_eq!(dfa.states.len(), 41);
+    assert_eq!(regex("([a-z]|[0-9]|_){1,10}x").grep("--ab_9x--", true), Some((2, 7)));
+}
+
+#[test]
+fn overlapping_classes_are_split() {
+    let regex = regex("a|[a-z]");
+
+    assert!(accepts(&regex, "a"));
+    assert!(accepts(&regex, "q"));
+    assert!(!accepts(&regex, "ab"));
+    assert_eq!(regex.grep_all("1a2b", true), vec![(1, 2), (3, 4)]);
+
+    let regex = self::regex("[a-m]x|[h-z]y");
+    assert!(accepts(&regex, "hx") && accepts(&regex, "hy"));
+    assert!(!accepts(&regex, "ay") && !accepts(&regex, "zx"));
+}
+
+#[test]
+fn head_state_can_accept() {
+    let dfa = dfa("a*");
+    assert!(matches!(dfa.states[dfa.head].kind, FaStateType::Tail));
+
+    let dfa = self::dfa("a+");
+    assert!(matches!(dfa.states[dfa.head].kind, FaStateType::Head));
+    assert_eq!(regex("a*").matcher("aab", true), Some(1));
+}
+
+#[test]
+fn bounded_repeats_end_after_the_last_copy() {
+    let regex = regex("ab{2,3}");
+    for (content, accepted) in [("ab", false), ("abb", true), ("abbb", true), ("abbbb", false)].iter() {
+        assert_eq!(accepts(&regex, content), *accepted, "{}", content);
+    }
+    assert_eq!(regex.grep("xabbbbx", true), Some((1, 5)));
+    assert_eq!(regex.grep("xabx", true), None);
+
+    let regex = self::regex("(ab){1,2}c");
+    assert!(accepts(&regex, "abc") && accepts(&regex, "ababc"));
+    assert!(!accepts(&regex, "c") && !accepts(&regex, "abababc"));
+}
+
+#[test]
+fn grep_all_agrees_with_the_per_offset_matcher() {
+    let cases = [
+        ("[a-z]+", "ab cd efg"),
+        ("a|ab", "xabab"),
+        ("ab{2,3}", "abbbb abb ab"),
+        ("(a|b)*c", "abcbbc c"),
+        ("x[0-9]?", "x1xx23x"),
+        ("a|[a-z]", "1a2bc"),
+    ];
+
+    for (pattern, content) in cases.iter() {
+        let regex = regex(pattern);
+        assert_eq!(regex.grep_all(content, true), grep_all_per_offset(&regex, content), "{}", pattern);
+    }
+}
@@ -0,0 +1,97 @@
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::nfa::Nfa;
+use lexer::serialize::*;
+
+fn build(pattern: &str) -> Dfa {
+    let expression = Expression::from_str(&pattern.to_string());
+    let nfa = Nfa::from_expression(&expression.units);
+
+    Dfa::from_nfa(&nfa.states, expression.get_chars())
+}
+
+const TEXTS: [&str; 9] = ["", "a", "ab", "abc", "x1", "é日", "if", "iff", "0x1f"];
+
+#[test]
+fn bytes_round_trip_to_an_equivalent_dfa() {
+    for pattern in ["[a-z]+[0-9]*", "(é|日)+", "if|[a-z]+", "0x[0-9a-f]+", "a?"] {
+        let dfa = build(pattern);
+        let bytes = dfa.to_bytes();
+        let copy = Dfa::from_bytes(&bytes).expect("Failed to deserialize dfa");
+        let view = DfaRef::from_bytes(&bytes).expect("Failed to deserialize dfa");
+
+        for text in TEXTS.iter() {
+            let state = text.chars().fold(view.start_state(), |state, ch| view.next_state(state, ch));
+            assert_eq!(copy.accepts(text), dfa.accepts(text), "{} {}", pattern, text);
+            assert_eq!(view.is_match_state(state), dfa.accepts(text), "{} {}", pattern, text);
+        }
+
+        assert_eq!(copy.to_bytes(), bytes);
+    }
+}
+
+#[test]
+fn view_works_on_unaligned_bytes() {
+    let bytes = build("[a-z]+").to_bytes();
+    let mut shifted = vec![0u8];
+    shifted.extend_from_slice(&bytes);
+
+    let view = DfaRef::from_bytes(&shifted[1..]).expect("Failed to deserialize dfa");
+    let state = "abc".chars().fold(view.start_state(), |state, ch| view.next_state(state, ch));
+    assert!(view.is_match_state(state));
+}
+
+#[test]
+fn header_errors_are_reported() {
+    let bytes = build("ab").to_bytes();
+
+    assert_eq!(DfaRef::from_bytes(b"nope").err(), Some(DeserializeError::BadMagic));
+
+    let mut swapped = bytes.clone();
+    swapped[8..12].reverse();
+    assert_eq!(DfaRef::from_bytes(&swapped).err(), Some(DeserializeError::WrongEndianness));
+
+    let mut version = bytes.clone();
+    version[12..16].copy_from_slice(&7u32.to_ne_bytes());
+    assert_eq!(DfaRef::from_bytes(&version).err(), Some(DeserializeError::UnsupportedVersion(7)));
+
+    let found = bytes.len() - 1;
+    assert_eq!(
+        DfaRef::from_bytes(&bytes[..found]).err(),
+        Some(DeserializeError::BadLength { expected: bytes.len(), found })
+    );
+}
+
+#[test]
+fn serializing_checks_the_counts_fit() {
+    let dfa = build("[a-z]+[0-9]*");
+    assert_eq!(dfa.try_to_bytes(), Ok(dfa.to_bytes()));
+
+    let error = SerializeError::TooBig { field: "state count", value: 1 << 32 };
+    assert_eq!(error.to_string(), "dfa state count 4294967296 does not fit in the format");
+}
+
+#[test]
+fn corrupted_bytes_never_panic() {
+    let bytes = build("[a-c]+x|é").to_bytes();
+
+    for len in 0..bytes.len() {
+        assert!(DfaRef::from_bytes(&bytes[..len]).is_err());
+    }
+
+    for i in 0..bytes.len() {
+        for flip in [0x01u8, 0x80, 0xff] {
+            let mut corrupted = bytes.clone();
+            corrupted[i] ^= flip;
+
+            if let Ok(view) = DfaRef::from_bytes(&corrupted) {
+                let mut state = view.start_state();
+                for ch in "abcxé\u{10FFFF}".chars() {
+                    state = view.next_state(state, ch);
+                    view.is_match_state(state);
+                }
+                view.to_dfa();
+            }
+        }
+    }
+}
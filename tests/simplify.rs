@@ -0,0 +1,87 @@
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::fastate::*;
+use lexer::nfa::Nfa;
+use std::collections::HashSet;
+use unit::*;
+
+const PATTERNS: [&str; 8] = ["a", "(a|b)*abb", "[a-z]+[0-9]*", "if|else|while", "a{3,5}", "(ab)*|abc", "a{2,}b?", "((a|b)*c)*"];
+
+fn parse(pattern: &str) -> (Nfa, HashSet<Char>) {
+    let expression = Expression::from_str(&pattern.to_string());
+    (Nfa::from_expression(&expression.units), expression.get_chars())
+}
+
+fn state(index: usize, trans: Vec<Tran>) -> FaState {
+    FaState {
+        index,
+        trans: trans.into_iter().collect(),
+        kind: FaStateType::Normal,
+    }
+}
+
+#[test]
+fn simplified_nfas_accept_the_same_strings() {
+    for pattern in PATTERNS.iter() {
+        let (nfa, chars) = parse(pattern);
+        let simple = nfa.simplify();
+
+        assert!(simple.states.len() <= nfa.states.len(), "{}", pattern);
+        assert!(simple.states.iter().all(|state| state.trans.iter().all(|tran| matches!(tran, Tran::Char(_, _)))));
+        assert_eq!(simple.head, 0);
+        assert_eq!(simple.tail, simple.states.len() - 1);
+
+        let lhs = Dfa::from_nfa(&nfa.states, chars.clone());
+        let rhs = Dfa::from_nfa(&simple.states, chars.clone());
+        assert_eq!(lhs.symmetric_difference(&rhs).shortest_match(), None, "{}", pattern);
+
+        let lhs = Dfa::from_nfa(&nfa.reverse().states, chars.clone());
+        let rhs = Dfa::from_nfa(&simple.reverse().states, chars);
+        assert_eq!(lhs.symmetric_difference(&rhs).shortest_match(), None, "{}", pattern);
+    }
+
+    // the head, one state per char and the old tail
+    assert_eq!(parse("(a|b)*abb").0.simplify().states.len(), 6);
+}
+
+#[test]
+fn trim_drops_unreachable_and_dead_states() {
+    // 2 is never reached and 3 never reaches the tail
+    let nfa = Nfa {
+        states: vec![
+            state(0, vec![Tran::Char(Char::Single('a'), 1), Tran::Char(Char::Single('b'), 3)]),
+            state(1, vec![Tran::Epsilon(4)]),
+            state(2, vec![Tran::Char(Char::Single('c'), 4)]),
+            state(3, vec![Tran::Char(Char::Single('d'), 3)]),
+            state(4, vec![]),
+        ],
+        head: 0,
+        tail: 4,
+    };
+
+    let trimmed = nfa.trim();
+    assert_eq!(trimmed.states.len(), 3);
+    assert_eq!(trimmed.tail, 2);
+    assert!(trimmed.states[0].trans.contains(&Tran::Char(Char::Single('a'), 1)));
+    assert_eq!(trimmed.states[0].trans.len(), 1);
+    assert!(trimmed.states[1].trans.contains(&Tran::Epsilon(2)));
+}
+
+#[test]
+fn merge_trans_joins_trans_to_the_same_state() {
+    let nfa = Nfa {
+        states: vec![
+            state(0, vec![Tran::Char(Char::Single('a'), 1), Tran::Char(Char::Single('b'), 1), Tran::Char(Char::Single('c'), 0)]),
+            state(1, vec![]),
+        ],
+        head: 0,
+        tail: 1,
+    };
+
+    let merged = nfa.merge_trans();
+    assert_eq!(merged.states[0].trans.len(), 2);
+    assert!(merged.states[0].trans.iter().any(|tran| match tran {
+        Tran::Char(ch, 1) => ch.to_ranges() == vec![('a', 'b')],
+        _ => false,
+    }));
+}
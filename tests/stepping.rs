@@ -0,0 +1,34 @@
+use lexer::dfa::StateId;
+use lexer::regex::*;
+
+#[test]
+fn stepping_follows_the_full_match_dfa() {
+    let regex = Regex::from(&String::from("ab*c|d"));
+    let dfa = regex.dfa();
+
+    let mut state = dfa.start_state();
+    assert!(!dfa.is_match_state(state));
+    for ch in "abbb".chars() {
+        state = dfa.next_state(state, ch);
+        assert!(!dfa.is_dead_state(state));
+        assert!(!dfa.is_match_state(state));
+    }
+    state = dfa.next_state(state, 'c');
+    assert!(dfa.is_match_state(state));
+
+    state = dfa.next_state(state, 'c');
+    assert!(dfa.is_dead_state(state));
+    assert!(!dfa.is_match_state(state));
+    assert_eq!(dfa.next_state(state, 'a'), StateId::DEAD);
+}
+
+#[test]
+fn stepping_agrees_with_accepts() {
+    let regex = Regex::from(&String::from("(a|é)+x?"));
+    let dfa = regex.dfa();
+
+    for text in ["", "a", "éaé", "ax", "axa", "x", "aé x"] {
+        let state = text.chars().fold(dfa.start_state(), |state, ch| dfa.next_state(state, ch));
+        assert_eq!(dfa.is_match_state(state), dfa.accepts(text), "{}", text);
+    }
+}
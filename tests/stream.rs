@@ -0,0 +1,106 @@
+use lexer::budget::*;
+use lexer::dfa::MatchKind;
+use lexer::regex::*;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+// Hands out the input a few bytes at a time, cutting multibyte chars in two.
+struct Trickle<'a> {
+    data: &'a [u8],
+    size: usize,
+}
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.size.min(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+fn build(pattern: &str, kind: MatchKind) -> Regex {
+    RegexBuilder::new(pattern).match_kind(kind).build()
+}
+
+fn stream(regex: &Regex, text: &str, size: usize) -> Vec<(usize, usize)> {
+    regex.stream_find_iter(Trickle { data: text.as_bytes(), size })
+        .collect::<io::Result<Vec<(usize, usize)>>>()
+        .expect("Failed to read stream")
+}
+
+#[test]
+fn stream_matches_agree_with_find_iter() {
+    let patterns = ["[0-9]+", "a*", "(ab|a)(c|bcd)", "é+|x", "a|b*", "[a-z]+@[a-z]+"];
+    let text = "xx aab 123 abcd ééé x bob@mail, a1b2 zz@ é";
+
+    for kind in [MatchKind::LeftmostFirst, MatchKind::LeftmostLongest, MatchKind::Earliest] {
+        for pattern in patterns.iter() {
+            let regex = build(pattern, kind);
+            let expected: Vec<(usize, usize)> = regex.find_iter(text).map(|m| (m.start(), m.end())).collect();
+
+            for size in 1..6 {
+                assert_eq!(stream(&regex, text, size), expected, "{} {:?} {}", pattern, kind, size);
+            }
+        }
+    }
+}
+
+#[test]
+fn stream_matches_span_chunk_boundaries() {
+    let regex = build("ab+c", MatchKind::LeftmostFirst);
+    let text = format!("{}a{}c", "x".repeat(10000), "b".repeat(20000));
+
+    assert_eq!(stream(&regex, &text, 4096), vec![(10000, 30002)]);
+}
+
+#[test]
+fn stream_rejects_invalid_utf8() {
+    let regex = build("a", MatchKind::LeftmostFirst);
+    let result: io::Result<Vec<(usize, usize)>> = regex.stream_find_iter(&b"a\xffa"[..]).collect();
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let result: io::Result<Vec<(usize, usize)>> = regex.stream_find_iter(&b"a\xc3"[..]).collect();
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn stream_reads_from_file() {
+    let path = std::env::temp_dir().join(format!("lexer-stream-{}.txt", std::process::id()));
+    let mut file = std::fs::File::create(&path).expect("Failed to create file");
+    file.write_all("let x = 42;\nlet y = 7;\n".as_bytes()).expect("Failed to write file");
+    drop(file);
+
+    let regex = build("[0-9]+", MatchKind::LeftmostFirst);
+    let file = std::fs::File::open(&path).expect("Failed to open file");
+    let found: Vec<(usize, usize)> = regex.stream_find_iter(file).map(|m| m.expect("Failed to read file")).collect();
+    std::fs::remove_file(&path).expect("Failed to remove file");
+
+    assert_eq!(found, vec![(8, 10), (20, 21)]);
+}
+
+#[test]
+fn budgeted_stream_agrees_within_the_budget() {
+    let regex = build("[a-z]+@[a-z]+", MatchKind::LeftmostFirst);
+    let text = "bob@mail, zz@ é a@b";
+    let mut budget = Budget::new();
+    budget.max_steps(10_000);
+
+    let found: Vec<(usize, usize)> = regex.try_stream_find_iter(Trickle { data: text.as_bytes(), size: 3 }, &mut budget)
+        .map(|m| m.expect("within the budget"))
+        .collect();
+    assert_eq!(found, stream(&regex, text, 3));
+}
+
+#[test]
+fn budget_stops_an_endless_stream() {
+    let regex = build("[0-9]+", MatchKind::LeftmostFirst);
+    let mut budget = Budget::new();
+    budget.max_steps(1_000_000);
+
+    let mut matches = regex.try_stream_find_iter(io::repeat(b'a'), &mut budget);
+    let error = matches.next().expect("an error").unwrap_err();
+    assert_eq!(error.get_ref().and_then(|inner| inner.downcast_ref::<SearchError>()), Some(&SearchError::StepLimit { limit: 1_000_000 }));
+    assert!(matches.next().is_none());
+}
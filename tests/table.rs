@@ -0,0 +1,48 @@
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::nfa::Nfa;
+use lexer::table::*;
+
+fn build(pattern: &str) -> Dfa {
+    let expression = Expression::from_str(&pattern.to_string());
+    let nfa = Nfa::from_expression(&expression.units);
+
+    Dfa::from_nfa(&nfa.states, expression.get_chars())
+}
+
+#[test]
+fn dense_and_sparse_tables_step_like_the_dfa() {
+    let texts = ["", "a", "abc", "zzé", "日本語ab", "a1b2c3", "\u{10FFFF}x", "\u{7f}\u{80}"];
+
+    for pattern in ["[a-c]+1?", "(é|日)+[^a]", "[0-9a-f]*x|[b-z]", "a(b|c)*"] {
+        let dfa = build(pattern);
+        let dense = DenseDfa::from_dfa(&dfa);
+        let sparse = SparseDfa::from_dfa(&dfa);
+
+        for text in texts.iter() {
+            let (mut s, mut d, mut p) = (dfa.start_state(), dense.start_state(), sparse.start_state());
+            for ch in text.chars() {
+                s = dfa.next_state(s, ch);
+                d = dense.next_state(d, ch);
+                p = sparse.next_state(p, ch);
+
+                assert_eq!(d, s, "{} {}", pattern, text);
+                assert_eq!(p, s, "{} {}", pattern, text);
+                assert_eq!(dense.is_match_state(d), dfa.is_match_state(s));
+                assert_eq!(sparse.is_match_state(p), dfa.is_match_state(s));
+            }
+        }
+    }
+}
+
+#[test]
+fn char_classes_cover_every_char() {
+    let dfa = build("[b-d]|é");
+    let classes = CharClasses::from_dfa(&dfa);
+
+    assert_eq!(classes.get('b'), classes.get('d'));
+    assert_ne!(classes.get('a'), classes.get('b'));
+    assert_eq!(classes.get('a'), classes.get('e'));
+    assert_eq!(classes.get('a'), classes.get('\u{10FFFF}'));
+    assert_ne!(classes.get('é'), classes.get('ê'));
+}
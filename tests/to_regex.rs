@@ -0,0 +1,65 @@
+use lexer::dfa::*;
+use lexer::expression::Expression;
+use lexer::regex::*;
+
+fn regex(pattern: &str) -> Regex {
+    Regex::from(pattern)
+}
+
+#[test]
+fn expressions_print_as_patterns() {
+    for pattern in ["ab|ac", "(a|b)*abb", "[a-z]+[0-9]*", "(é|日本)+x?", "a{2,3}", "[^a-c]|b", "(?P<y>[0-9])x"] {
+        assert_eq!(Expression::from_str(&pattern.to_string()).to_string(), pattern);
+    }
+}
+
+#[test]
+fn to_regex_round_trips() {
+    let patterns = ["ab|ac", "(a|b)*abb", "[a-z]+[0-9]*", "if|else|while", "(é|日本)+x?", "a?", "[^a-c]|b", "(ab)*|abc", "a*"];
+
+    for pattern in patterns.iter() {
+        let original = regex(pattern);
+        let expression = original.dfa().to_regex().expect("language is not empty");
+
+        assert_eq!(regex(&expression.to_string()).is_equivalent(&original), Ok(()), "{} -> {}", pattern, expression.to_string());
+    }
+}
+
+#[test]
+fn to_regex_simplifies() {
+    assert_eq!(regex("ab|ac").dfa().to_regex().unwrap().to_string(), "a[b-c]");
+    assert_eq!(regex("[a-z]+[0-9]*").dfa().to_regex().unwrap().to_string(), "[a-z]+[0-9]*");
+    assert_eq!(regex("(a|a)*|a").dfa().to_regex().unwrap().to_string(), "a*");
+}
+
+#[test]
+fn operations_can_be_shown_as_patterns() {
+    let names = regex("[a-z]+").dfa().difference(regex("if").dfa());
+    let shown = names.to_regex().unwrap().to_string();
+    assert_eq!(regex(&shown).dfa().symmetric_difference(&names).shortest_match(), None, "{}", shown);
+
+    let nothing: Dfa = regex("a").dfa().intersection(regex("b").dfa());
+    assert!(nothing.to_regex().is_none());
+
+    let empty = regex("a*").dfa().difference(regex("a+").dfa()).to_regex().unwrap();
+    assert_eq!(empty.to_string(), "a{0}");
+    assert!(regex(&empty.to_string()).dfa().accepts(""));
+    assert!(!regex(&empty.to_string()).dfa().accepts("a"));
+}
+
+#[test]
+fn brackets_and_backslashes_print_back() {
+    for (pattern, shown) in [("\\[", "[[]"), ("\\]", "\\]"), ("\\{x\\}", "[{]x[}]"), ("\\\\", "[\\]"), ("[{}]", "[{}]")] {
+        let expression = Expression::from_str(&pattern.to_string());
+        assert_eq!(expression.to_string(), shown);
+        assert_eq!(Expression::from_str(&expression.to_string()).to_string(), shown);
+    }
+
+    let patterns = ["\\[a\\]", "\\{|\\}|\\\\", "[Z-a]+", "x(\\]|\\[)*y", "[\\[{]*\\]"];
+    for pattern in patterns.iter() {
+        let original = regex(pattern);
+        let expression = original.dfa().to_regex().expect("language is not empty");
+
+        assert_eq!(regex(&expression.to_string()).is_equivalent(&original), Ok(()), "{} -> {}", pattern, expression.to_string());
+    }
+}